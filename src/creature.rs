@@ -4,6 +4,13 @@ pub mod size;
 pub mod damage;
 pub mod alignment;
 pub mod health;
+pub mod stats;
+pub mod traits;
+pub mod attack;
+pub mod action;
+pub mod check;
+pub mod modifier;
+pub mod source;
 
 pub use self::{
     ability::*,
@@ -12,24 +19,190 @@ pub use self::{
     damage::*,
     alignment::*,
     health::*,
+    stats::*,
+    traits::*,
+    attack::*,
+    action::*,
+    check::*,
+    modifier::*,
+    source::*,
 };
 
 use crate::world;
+use crate::world::space::Feet;
 
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
-pub struct CID(usize);
+pub struct CID(pub(crate) usize);
 
+// A stat block: everything about a kind of creature that doesn't change during a fight.
+// Proficiencies not listed in `saves` and `skills` are untrained.
+#[derive(Debug,Clone)]
 pub struct Creature {
-    scores: AbilityScores,
-    align: Alignment,
-    dmgmods: damage::Modifiers,
+    pub name: String,
+    pub level: isize,
+    pub size: Size,
+    pub scores: AbilityScores,
+    pub align: Alignment,
+    pub traits: HashSet<Trait>,
+    pub max_hp: usize,
+    pub dmgmods: damage::Modifiers,
+    // Effects with any of these traits don't take hold; see Creature::immune_to().
+    pub effect_immunities: HashSet<EffectTrait>,
+    pub armor: Armor,
+    pub perception: Proficiency,
+    pub saves: HashMap<Save, Proficiency>,
+    pub skills: HashMap<Skill, Proficiency>,
+    pub speeds: HashMap<SpeedType, Feet>,
+    pub senses: Vec<Sense>,
+    pub languages: Vec<String>,
+    pub attacks: Vec<Attack>,
+    pub reactions: Vec<Reaction>,
 }
 
+// Which way a creature fights; enemies win initiative ties against the party.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Side {
+    Party,
+    Neutral,
+    Enemy,
+}
+
+// A creature as it exists in a World.
+#[derive(Debug,Clone)]
 pub struct State {
-    id: CID,
-    creature: Rc<Creature>,
-    loc: world::space::Location,
-    health: Health,
+    pub id: CID,
+    pub creature: Rc<Creature>,
+    pub side: Side,
+    pub loc: world::space::Location,
+    pub health: Health,
+    pub status: Status,
+    pub budget: Budget,
+}
+
+impl Creature {
+    // A medium, unarmored creature with no proficiencies beyond its armor, a 25-foot land
+    // speed and nothing else; fill in the rest by field.
+    pub fn new(name: &str, level: isize, scores: AbilityScores, align: Alignment, max_hp: usize) -> Self {
+        let mut speeds = HashMap::new();
+        speeds.insert(SpeedType::Land, Feet(25));
+        Self {
+            name: name.to_string(),
+            level,
+            size: Size::Medium,
+            scores,
+            align,
+            traits: HashSet::new(),
+            max_hp,
+            dmgmods: Default::default(),
+            effect_immunities: HashSet::new(),
+            armor: Default::default(),
+            perception: Proficiency::Untrained,
+            saves: HashMap::new(),
+            skills: HashMap::new(),
+            speeds,
+            senses: Vec::new(),
+            languages: Vec::new(),
+            attacks: Vec::new(),
+            reactions: Vec::new(),
+        }
+    }
+
+    pub fn mods(&self) -> AbilityMods {
+        self.scores.into()
+    }
+
+    fn modifier(&self, ab: Ability, prof: Proficiency) -> isize {
+        self.mods().get(ab) + prof.bonus(self.level)
+    }
+
+    pub fn ac(&self) -> isize {
+        let dex = self.mods().get(Ability::Dex);
+        let dex = match self.armor.dex_cap {
+            Some(cap) => std::cmp::min(dex, cap),
+            None => dex,
+        };
+        10 + dex + self.armor.prof.bonus(self.level) + self.armor.item_bonus
+    }
+
+    pub fn save_prof(&self, save: Save) -> Proficiency {
+        self.saves.get(&save).copied().unwrap_or(Proficiency::Untrained)
+    }
+
+    pub fn save(&self, save: Save) -> isize {
+        self.modifier(save.ability(), self.save_prof(save))
+    }
+
+    pub fn perception(&self) -> isize {
+        self.modifier(Ability::Wis, self.perception)
+    }
+
+    pub fn skill_prof(&self, skill: Skill) -> Proficiency {
+        self.skills.get(&skill).copied().unwrap_or(Proficiency::Untrained)
+    }
+
+    pub fn skill(&self, skill: Skill) -> isize {
+        self.modifier(skill.ability(), self.skill_prof(skill))
+    }
+
+    // The DC others roll against to affect this creature with the given statistic.
+    pub fn dc(modifier: isize) -> isize {
+        10 + modifier
+    }
+
+    pub fn speed(&self, tp: SpeedType) -> Option<Feet> {
+        self.speeds.get(&tp).copied()
+    }
+
+    pub fn has_trait(&self, tr: &Trait) -> bool {
+        self.traits.contains(tr)
+    }
+
+    pub fn vitality(&self) -> Vitality {
+        if self.has_trait(&Trait::Undead) {
+            Vitality::Undead
+        } else if self.has_trait(&Trait::Construct) {
+            Vitality::Neither
+        } else {
+            Vitality::Living
+        }
+    }
+
+    // The longest reach among its melee attacks, if it has any.
+    pub fn reach(&self) -> Option<Feet> {
+        self.attacks.iter().filter_map(Attack::reach).max()
+    }
+
+    pub fn target(&self) -> Target {
+        Target { align: self.align, vitality: self.vitality() }
+    }
+}
+
+impl Side {
+    // Whether creatures on the two sides fight each other; neutrals are on nobody's side.
+    pub fn opposes(self, other: Side) -> bool {
+        matches!((self, other), (Side::Party, Side::Enemy) | (Side::Enemy, Side::Party))
+    }
+}
+
+impl State {
+    pub fn new(id: CID, creature: Rc<Creature>, side: Side, loc: world::space::Location) -> Self {
+        let health = Health { hp: creature.max_hp, max_hp: creature.max_hp, temp_hp: 0 };
+        Self { id, creature, side, loc, health, status: Status::new(), budget: Default::default() }
+    }
+
+    pub fn footprint(&self) -> Vec<world::space::Location> {
+        self.creature.size.footprint(self.loc)
+    }
+
+    // Between the nearest squares of the two creatures' spaces.
+    pub fn distance(&self, other: &State) -> world::space::Squares {
+        let theirs = other.footprint();
+        self.footprint().into_iter()
+            .flat_map(|mine| theirs.iter().map(move |&t| mine.distance(t)))
+            .min()
+            .unwrap()
+    }
 }
@@ -8,6 +8,16 @@ pub struct Abilities {
     pub _cha: isize,
 }
 
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Ability {
+    Str,
+    Dex,
+    Con,
+    Int,
+    Wis,
+    Cha,
+}
+
 #[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
 pub struct AbilityScores(Abilities);
 #[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
@@ -16,12 +26,48 @@ pub struct AbilityMods(Abilities);
 impl From<AbilityScores> for AbilityMods {
     fn from(ab: AbilityScores) -> Self {
         Self(Abilities {
-            _str: ((ab.0)._str - 10) / 2,
-            _dex: ((ab.0)._dex - 10) / 2,
-            _con: ((ab.0)._con - 10) / 2,
-            _int: ((ab.0)._int - 10) / 2,
-            _wis: ((ab.0)._wis - 10) / 2,
-            _cha: ((ab.0)._cha - 10) / 2,
+            _str: ((ab.0)._str - 10).div_euclid(2),
+            _dex: ((ab.0)._dex - 10).div_euclid(2),
+            _con: ((ab.0)._con - 10).div_euclid(2),
+            _int: ((ab.0)._int - 10).div_euclid(2),
+            _wis: ((ab.0)._wis - 10).div_euclid(2),
+            _cha: ((ab.0)._cha - 10).div_euclid(2),
         })
     }
 }
+
+impl Abilities {
+    pub fn get(&self, ab: Ability) -> isize {
+        use Ability::*;
+
+        match ab {
+            Str => self._str,
+            Dex => self._dex,
+            Con => self._con,
+            Int => self._int,
+            Wis => self._wis,
+            Cha => self._cha,
+        }
+    }
+}
+
+impl AbilityScores {
+    pub fn new(scores: Abilities) -> Self {
+        Self(scores)
+    }
+
+    pub fn get(&self, ab: Ability) -> isize {
+        self.0.get(ab)
+    }
+}
+
+impl AbilityMods {
+    // Bestiary entries usually give modifiers directly rather than scores.
+    pub fn new(mods: Abilities) -> Self {
+        Self(mods)
+    }
+
+    pub fn get(&self, ab: Ability) -> isize {
+        self.0.get(ab)
+    }
+}
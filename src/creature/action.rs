@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+
+use serde::{Serialize, Deserialize};
+
+use super::Current;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Cost {
+    Free,
+    Reaction,
+    // One, two or three actions; anything else is invalid.
+    Actions(usize),
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ActionTrait {
+    Attack,
+    Concentrate,
+    Manipulate,
+    Move,
+    // Not a trait as printed, but what reactions to ranged attacks look for.
+    Ranged,
+}
+
+// Anything a creature does with its actions: a single action or a multi-action activity.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Activity {
+    pub name: String,
+    // Which basic action or activity this is, whatever it's called: every Strike is a STRIKE.
+    pub kind: String,
+    pub cost: Cost,
+    pub traits: HashSet<ActionTrait>,
+    // What it's used in response to, for free actions that have one.
+    pub trigger: Option<Trigger>,
+}
+
+// What sets off a reaction, seen from the creature that has it.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum Trigger {
+    // Another creature within reach of one of our melee attacks uses an activity with any of
+    // these traits.
+    InReach(HashSet<ActionTrait>),
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Reaction {
+    pub name: String,
+    pub trigger: Trigger,
+}
+
+// What's left of a creature's actions this turn. The reaction comes back at the start of each of
+// its turns, and is kept across other creatures' turns until then.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct Budget {
+    pub actions: usize,
+    pub reaction: bool,
+    // Actions with the attack trait used this turn, for the multiple attack penalty.
+    pub attacks: usize,
+    // How many of `actions` can only go to the activities quickened allows.
+    pub restricted: usize,
+}
+
+impl Cost {
+    pub const MAX_ACTIONS: usize = 3;
+
+    pub fn is_valid(self) -> bool {
+        match self {
+            Cost::Actions(n) => (1 ..= Self::MAX_ACTIONS).contains(&n),
+            _ => true,
+        }
+    }
+}
+
+impl Activity {
+    pub const STRIDE: &'static str = "Stride";
+    pub const STRIKE: &'static str = "Strike";
+    pub const INTERACT: &'static str = "Interact";
+    pub const SUSTAIN: &'static str = "Sustain";
+
+    // Of its own kind, unless given another with of_kind().
+    pub fn new(name: &str, cost: Cost) -> Self {
+        Self { name: name.to_string(), kind: name.to_string(), cost, traits: HashSet::new(), trigger: None }
+    }
+
+    pub fn of_kind(mut self, kind: &str) -> Self {
+        self.kind = kind.to_string();
+        self
+    }
+
+    pub fn with_trait(mut self, tr: ActionTrait) -> Self {
+        self.traits.insert(tr);
+        self
+    }
+
+    pub fn with_trigger(mut self, trigger: Trigger) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    pub fn has_trait(&self, tr: ActionTrait) -> bool {
+        self.traits.contains(&tr)
+    }
+
+    pub fn stride() -> Self {
+        Self::new(Self::STRIDE, Cost::Actions(1)).with_trait(ActionTrait::Move)
+    }
+
+    pub fn interact() -> Self {
+        Self::new(Self::INTERACT, Cost::Actions(1)).with_trait(ActionTrait::Manipulate)
+    }
+
+    pub fn sustain() -> Self {
+        Self::new(Self::SUSTAIN, Cost::Actions(1)).with_trait(ActionTrait::Concentrate)
+    }
+}
+
+impl Trigger {
+    pub fn matches(&self, act: &Activity) -> bool {
+        match self {
+            Trigger::InReach(traits) => !traits.is_disjoint(&act.traits),
+        }
+    }
+}
+
+impl Reaction {
+    pub const ATTACK_OF_OPPORTUNITY: &'static str = "attack-of-opportunity";
+
+    pub fn attack_of_opportunity() -> Self {
+        use ActionTrait::*;
+
+        Self {
+            name: Self::ATTACK_OF_OPPORTUNITY.to_string(),
+            trigger: Trigger::InReach([Manipulate, Move, Ranged].iter().copied().collect()),
+        }
+    }
+
+    // The reactions a stat block can name.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            Self::ATTACK_OF_OPPORTUNITY => Some(Self::attack_of_opportunity()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self { actions: 0, reaction: true, attacks: 0, restricted: 0 }
+    }
+}
+
+impl Budget {
+    // A fresh turn's worth, after slowed, stunned and quickened; those are taken from the
+    // quickened action last.
+    pub fn for_turn(cur: &Current) -> Self {
+        let restricted = if cur.quickened.is_some() { std::cmp::min(cur.actions_gained, 1) } else { 0 };
+        Self { actions: cur.actions_gained, reaction: true, attacks: 0, restricted }
+    }
+}
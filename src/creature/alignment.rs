@@ -41,3 +41,32 @@ pub struct Alignment {
     pub ge: GoodEvil,
     pub lc: LawChaos,
 }
+
+// Parses the usual abbreviations: "LG", "NG", "CG", "LN", "N", "CN", "LE", "NE", "CE".
+impl std::str::FromStr for Alignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let upper = s.trim().to_uppercase();
+        if upper == "N" {
+            return Ok(Alignment { ge: GoodEvil::Neutral, lc: LawChaos::Neutral });
+        }
+        let mut chars = upper.chars();
+        let lc = match chars.next() {
+            Some('L') => LawChaos::Lawful,
+            Some('N') => LawChaos::Neutral,
+            Some('C') => LawChaos::Chaotic,
+            _ => return Err(format!("unknown alignment {:?}", s)),
+        };
+        let ge = match chars.next() {
+            Some('G') => GoodEvil::Good,
+            Some('N') => GoodEvil::Neutral,
+            Some('E') => GoodEvil::Evil,
+            _ => return Err(format!("unknown alignment {:?}", s)),
+        };
+        if chars.next().is_some() || (lc == LawChaos::Neutral && ge == GoodEvil::Neutral) {
+            return Err(format!("unknown alignment {:?}", s));
+        }
+        Ok(Alignment { ge, lc })
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+
+use super::{Creature, Ability, Activity, ActionTrait, Cost, CritTraits, Proficiency, DamagePacket};
+use crate::world::space::Feet;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum AttackRange {
+    Melee { reach: Feet },
+    Ranged { increment: Feet },
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum WeaponTrait {
+    Agile,
+    Finesse,
+    Unarmed,
+    // Die sizes, as for CritTraits.
+    Deadly(usize),
+    Fatal(usize),
+    // Overrides the reach given by the attack's range.
+    Reach(Feet),
+}
+
+// One entry in a creature's list of Strikes: a weapon or unarmed attack. Its damage is as printed
+// in the stat block, ability modifier included.
+#[derive(Debug,Clone)]
+pub struct Attack {
+    pub name: String,
+    pub range: AttackRange,
+    pub prof: Proficiency,
+    pub item_bonus: isize,
+    pub traits: Vec<WeaponTrait>,
+    pub damage: DamagePacket,
+}
+
+impl Attack {
+    // Ranged attacks can't reach past this many range increments.
+    pub const MAX_INCREMENTS: usize = 6;
+
+    pub fn has_trait(&self, tr: WeaponTrait) -> bool {
+        self.traits.contains(&tr)
+    }
+
+    pub fn is_melee(&self) -> bool {
+        matches!(self.range, AttackRange::Melee { .. })
+    }
+
+    pub fn reach(&self) -> Option<Feet> {
+        match self.range {
+            AttackRange::Melee { reach } => Some(self.traits.iter()
+                .find_map(|tr| match tr {
+                    WeaponTrait::Reach(r) => Some(*r),
+                    _ => None,
+                })
+                .unwrap_or(reach)),
+            AttackRange::Ranged { .. } => None,
+        }
+    }
+
+    pub fn crit_traits(&self) -> CritTraits {
+        let mut res = CritTraits::default();
+        for tr in &self.traits {
+            match *tr {
+                WeaponTrait::Deadly(die) => res.deadly = Some(die),
+                WeaponTrait::Fatal(die) => res.fatal = Some(die),
+                _ => (),
+            }
+        }
+        res
+    }
+
+    // The multiple attack penalty for this attack, given how many the attacker already made this
+    // turn.
+    pub fn map(&self, attacks_made: usize) -> isize {
+        let agile = self.has_trait(WeaponTrait::Agile);
+        match (attacks_made, agile) {
+            (0, _) => 0,
+            (1, false) => -5,
+            (1, true) => -4,
+            (_, false) => -10,
+            (_, true) => -8,
+        }
+    }
+
+    pub fn activity(&self) -> Activity {
+        let act = Activity::new(&format!("Strike ({})", self.name), Cost::Actions(1))
+            .of_kind(Activity::STRIKE)
+            .with_trait(ActionTrait::Attack);
+        if self.is_melee() { act } else { act.with_trait(ActionTrait::Ranged) }
+    }
+}
+
+impl Creature {
+    // Strength for melee, Dexterity for ranged; finesse melee attacks use whichever is better.
+    pub fn attack_ability(&self, atk: &Attack) -> Ability {
+        let mods = self.mods();
+        match atk.range {
+            AttackRange::Melee { .. } if atk.has_trait(WeaponTrait::Finesse) && mods.get(Ability::Dex) > mods.get(Ability::Str) => Ability::Dex,
+            AttackRange::Melee { .. } => Ability::Str,
+            AttackRange::Ranged { .. } => Ability::Dex,
+        }
+    }
+
+    pub fn attack_bonus(&self, atk: &Attack) -> isize {
+        self.mods().get(self.attack_ability(atk)) + atk.prof.bonus(self.level) + atk.item_bonus
+    }
+}
+
+// Parses e.g. "agile", "deadly-d10" or "reach-15".
+impl std::str::FromStr for WeaponTrait {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let bad = || format!("unknown weapon trait {:?}", s);
+        let die = |d: &str| d.strip_prefix('d').and_then(|n| n.parse().ok()).filter(|&n: &usize| n > 0);
+        match s {
+            "agile" => Ok(WeaponTrait::Agile),
+            "finesse" => Ok(WeaponTrait::Finesse),
+            "unarmed" => Ok(WeaponTrait::Unarmed),
+            _ => match s.split_once('-') {
+                Some(("deadly", d)) => die(d).map(WeaponTrait::Deadly).ok_or_else(bad),
+                Some(("fatal", d)) => die(d).map(WeaponTrait::Fatal).ok_or_else(bad),
+                Some(("reach", ft)) => ft.parse().map(|ft| WeaponTrait::Reach(Feet(ft))).map_err(|_| bad()),
+                _ => Err(bad()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for WeaponTrait {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeaponTrait::Agile => write!(f, "agile"),
+            WeaponTrait::Finesse => write!(f, "finesse"),
+            WeaponTrait::Unarmed => write!(f, "unarmed"),
+            WeaponTrait::Deadly(die) => write!(f, "deadly-d{}", die),
+            WeaponTrait::Fatal(die) => write!(f, "fatal-d{}", die),
+            WeaponTrait::Reach(ft) => write!(f, "reach-{}", ft.0),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+
+use super::{Creature, Current, ModifierSet, Resolved, Save, Skill, Stat};
+use crate::rng::{RandState, RandValue, Roll, Roller};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub enum Degree {
+    CriticalFailure,
+    Failure,
+    Success,
+    CriticalSuccess,
+}
+
+// One named contribution to a check's total or its DC.
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+pub struct Term {
+    pub name: String,
+    pub value: isize,
+}
+
+// `stat` decides which typed modifiers apply to either side; flat checks (None) take none.
+// `versus` is the statistic whose DC is being rolled against, where that matters.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Check {
+    pub stat: Option<Stat>,
+    pub versus: Option<Stat>,
+    pub modifiers: Vec<Term>,
+    pub dc: Vec<Term>,
+}
+
+#[derive(Debug,Clone)]
+pub struct CheckResult {
+    pub roll: Roll,
+    pub modifiers: Vec<Term>,
+    pub dc_terms: Vec<Term>,
+    pub total: isize,
+    pub dc: isize,
+    pub degree: Degree,
+}
+
+impl Degree {
+    // Ignoring the die: 10 over the DC is a critical success, 10 under a critical failure.
+    pub fn of(total: isize, dc: isize) -> Self {
+        if total >= dc + 10 {
+            Degree::CriticalSuccess
+        } else if total >= dc {
+            Degree::Success
+        } else if total <= dc - 10 {
+            Degree::CriticalFailure
+        } else {
+            Degree::Failure
+        }
+    }
+
+    // Moves up (or down, if negative) by `steps`, stopping at either end.
+    pub fn shift(self, steps: isize) -> Self {
+        use Degree::*;
+
+        const ALL: [Degree; 4] = [CriticalFailure, Failure, Success, CriticalSuccess];
+        let idx = (self as isize + steps).clamp(0, 3);
+        ALL[idx as usize]
+    }
+
+    // A natural 20 improves the degree one step, and a natural 1 worsens it.
+    pub fn with_natural(self, natural: isize) -> Self {
+        match natural {
+            20 => self.shift(1),
+            1 => self.shift(-1),
+            _ => self,
+        }
+    }
+
+    pub fn is_success(self) -> bool {
+        self >= Degree::Success
+    }
+}
+
+impl Term {
+    pub fn new(name: &str, value: isize) -> Self {
+        Self { name: name.to_string(), value }
+    }
+}
+
+impl Check {
+    pub fn new(stat: Stat, name: &str, modifier: isize) -> Self {
+        Self { stat: Some(stat), versus: None, modifiers: vec![Term::new(name, modifier)], dc: Vec::new() }
+    }
+
+    pub fn flat() -> Self {
+        Self { stat: None, versus: None, modifiers: Vec::new(), dc: Vec::new() }
+    }
+
+    // What the DC side of this check is made of: AC for attacks, the DC of whatever it's versus,
+    // or a DC keyed to no ability in particular.
+    pub fn dc_stat(&self) -> Option<Stat> {
+        match (self.stat?, self.versus) {
+            (_, Some(stat)) => Some(stat.dc()),
+            (Stat::Attack(_), None) => Some(Stat::Ac),
+            _ => Some(Stat::Dc(None)),
+        }
+    }
+
+    pub fn against(mut self, name: &str, dc: isize) -> Self {
+        self.dc.push(Term::new(name, dc));
+        self
+    }
+
+    // Against the DC of one of the target's statistics, e.g. Stat::Save(Save::Will) for
+    // Demoralize, so that modifiers keyed to its ability apply to the DC.
+    pub fn versus(mut self, name: &str, stat: Stat, dc: isize) -> Self {
+        self.versus = Some(stat);
+        self.against(name, dc)
+    }
+
+    pub fn modifier(mut self, name: &str, value: isize) -> Self {
+        if self.stat.is_some() && value != 0 {
+            self.modifiers.push(Term::new(name, value));
+        }
+        self
+    }
+
+    pub fn dc_modifier(mut self, name: &str, value: isize) -> Self {
+        if self.stat.is_some() && value != 0 {
+            self.dc.push(Term::new(name, value));
+        }
+        self
+    }
+
+    // Adds whatever survives stacking from the roller's modifiers.
+    pub fn modifiers(mut self, mods: &ModifierSet) -> Self {
+        if let Some(stat) = self.stat {
+            self.modifiers.extend(terms(mods.resolve(stat)));
+        }
+        self
+    }
+
+    // As modifiers(), for the side the DC belongs to.
+    pub fn dc_modifiers(mut self, mods: &ModifierSet) -> Self {
+        if let Some(stat) = self.dc_stat() {
+            self.dc.extend(terms(mods.resolve(stat)));
+        }
+        self
+    }
+
+    // Applies the conditions of whoever is rolling.
+    pub fn roller(self, cur: &Current) -> Self {
+        self.modifiers(&cur.modifiers)
+    }
+
+    // Applies the conditions of whoever the DC belongs to.
+    pub fn target(self, cur: &Current) -> Self {
+        self.dc_modifiers(&cur.modifiers)
+    }
+
+    pub fn modifier_total(&self) -> isize {
+        self.modifiers.iter().map(|t| t.value).sum()
+    }
+
+    pub fn dc_total(&self) -> isize {
+        self.dc.iter().map(|t| t.value).sum()
+    }
+
+    pub fn roll<R: Roller>(&self, rng: &mut RandState<R>) -> CheckResult {
+        let roll = RandValue::D20.roll(rng);
+        let total = roll.total + self.modifier_total();
+        let dc = self.dc_total();
+        CheckResult {
+            degree: Degree::of(total, dc).with_natural(roll.total),
+            roll,
+            modifiers: self.modifiers.clone(),
+            dc_terms: self.dc.clone(),
+            total,
+            dc,
+        }
+    }
+
+    // Chances of each degree, worst first.
+    pub fn chances(&self) -> [f64; 4] {
+        let mut res = [0.0; 4];
+        for natural in 1 ..= 20 {
+            let degree = Degree::of(natural + self.modifier_total(), self.dc_total()).with_natural(natural);
+            res[degree as usize] += 1.0 / 20.0;
+        }
+        res
+    }
+}
+
+fn terms(res: Resolved) -> impl Iterator<Item = Term> {
+    res.applied.into_iter().map(|m| Term { name: format!("{} {}", m.tp, m.name), value: m.value })
+}
+
+impl Creature {
+    pub fn save_check(&self, save: Save) -> Check {
+        Check::new(Stat::Save(save), &format!("{:?}", save), self.save(save))
+    }
+
+    pub fn skill_check(&self, skill: Skill) -> Check {
+        Check::new(Stat::Skill(skill), &format!("{:?}", skill), self.skill(skill))
+    }
+
+    pub fn perception_check(&self) -> Check {
+        Check::new(Stat::Perception, "Perception", self.perception())
+    }
+}
+
+impl CheckResult {
+    pub fn natural(&self) -> isize {
+        self.roll.total
+    }
+}
+
+impl fmt::Display for Degree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Degree::CriticalFailure => "critical failure",
+            Degree::Failure => "failure",
+            Degree::Success => "success",
+            Degree::CriticalSuccess => "critical success",
+        })
+    }
+}
+
+fn write_terms(f: &mut fmt::Formatter, terms: &[Term]) -> fmt::Result {
+    for t in terms {
+        if t.value < 0 {
+            write!(f, " - {} ({})", -t.value, t.name)?;
+        } else {
+            write!(f, " + {} ({})", t.value, t.name)?;
+        }
+    }
+    Ok(())
+}
+
+// e.g. "d20[14] + 7 (Athletics) - 1 (status frightened) = 20 vs DC 18: success"
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "d20[{}]", self.natural())?;
+        write_terms(f, &self.modifiers)?;
+        write!(f, " = {} vs DC {}", self.total, self.dc)?;
+        if self.dc_terms.len() > 1 {
+            write!(f, " (")?;
+            let (first, rest) = self.dc_terms.split_first().unwrap();
+            write!(f, "{} {}", first.value, first.name)?;
+            write_terms(f, rest)?;
+            write!(f, ")")?;
+        }
+        write!(f, ": {}", self.degree)
+    }
+}
@@ -3,8 +3,12 @@ use std::collections::{HashMap, HashSet};
 
 use super::alignment::*;
 use crate::rng::*;
+use crate::world::space::{Location, Squares};
 
-#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum DamageType {
     Slashing,
     Piercing,
@@ -15,6 +19,9 @@ pub enum DamageType {
     Electricity,
     Fire,
     Sonic,
+    Force,
+    Positive,
+    Negative,
     Chaotic,
     Evil,
     Good,
@@ -23,7 +30,8 @@ pub enum DamageType {
     Poison,
 }
 
-#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum DamageKind {
     Physical,
     Energy,
@@ -32,10 +40,19 @@ pub enum DamageKind {
     Poison,
 }
 
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Material {
+    ColdIron,
+    Silver,
+    Adamantine,
+}
+
 #[derive(Debug,Clone)]
 pub struct Damage {
     pub tp: DamageType,
     pub magical: bool,
+    pub material: Option<Material>,
     pub amount: RandValue,
     pub prec_amount: Option<RandValue>,
 }
@@ -44,26 +61,95 @@ pub struct Damage {
 pub struct DamageResult {
     pub tp: DamageType,
     pub magical: bool,
+    pub material: Option<Material>,
     pub amount: usize,
     pub prec_amount: usize,
 }
 
+// Weapon traits that change damage on a critical hit, each given as a die size. Deadly adds dice
+// of that size after doubling; fatal resizes all the weapon's dice and adds one more of that size.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Default)]
+pub struct CritTraits {
+    pub deadly: Option<usize>,
+    pub fatal: Option<usize>,
+}
+
+// Splash damage is dealt to the target and everything within 5 feet of it, even on a miss (but
+// not a critical miss), and is never doubled. The target's share is added to the rest of its
+// damage, so resistances apply to both together.
+#[derive(Debug,Clone)]
+pub struct Splash {
+    pub damage: Damage,
+}
+
+// Everything one Strike, spell or hazard deals at once, e.g. 1d8+4 slashing, 1d6 fire and 1d6
+// persistent bleed. Persistent components aren't rolled with the rest; they are for the caller to
+// attach to the target as PersistentDamage effects; splash is likewise left to the caller, since
+// it lands on everything around the target too.
+#[derive(Debug,Clone,Default)]
+pub struct DamagePacket {
+    pub components: Vec<Damage>,
+    pub persistent: Vec<Damage>,
+    pub splash: Option<Splash>,
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct PacketResult {
+    // What the target actually loses, for Health::take_damage.
+    pub total: usize,
+    // Damage remaining per type, after immunity, weakness and resistance.
+    pub parts: Vec<DamageResult>,
+    pub weakness: Option<usize>,
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct DamageRoll {
+    pub result: DamageResult,
+    pub amount: Roll,
+    pub prec_amount: Option<Roll>,
+}
+
 #[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
 pub enum Spec {
     Type(DamageType),
     Kind(DamageKind),
+    Material(Material),
+    Magical,
     NonMagical,
     Precision,
     All,
 }
 
-#[derive(Debug,Clone)]
+// A resistance that doesn't apply to damage matching any of `except`, as in "physical 5 (except
+// silver)" or "all 10 (except force)".
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Resistance {
+    pub amount: usize,
+    pub except: Vec<Spec>,
+}
+
+#[derive(Debug,Clone,Default)]
 pub struct Modifiers {
-    pub resistances: HashMap<Spec, usize>,
+    pub resistances: HashMap<Spec, Resistance>,
     pub weaknesses: HashMap<Spec, usize>,
     pub immunities: HashSet<Spec>,
 }
 
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Vitality {
+    Living,
+    Undead,
+    // Constructs and the like, harmed by neither positive nor negative energy.
+    Neither,
+}
+
+// What damage application needs to know about the creature being hit, beyond its Modifiers.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct Target {
+    pub align: Alignment,
+    pub vitality: Vitality,
+}
+
 #[derive(Debug,Clone,Copy,Default)]
 struct ModTest {
     resistance: Option<usize>,
@@ -77,7 +163,7 @@ impl DamageType {
 
         match self {
             Slashing | Piercing | Bludgeoning | Bleed => DamageKind::Physical,
-            Acid | Cold | Electricity | Fire | Sonic => DamageKind::Energy,
+            Acid | Cold | Electricity | Fire | Sonic | Force | Positive | Negative => DamageKind::Energy,
             Chaotic | Evil | Good | Lawful => DamageKind::Aligned,
             Mental => DamageKind::Mental,
             Poison => DamageKind::Poison,
@@ -95,55 +181,388 @@ impl DamageType {
             _ => false,
         }
     }
+
+    // Whether damage of this type can hurt the target at all: aligned damage only hurts the
+    // opposed alignment, positive only hurts the undead, and negative only hurts the living.
+    pub fn affects(self, target: &Target) -> bool {
+        use DamageType::*;
+
+        match self {
+            Chaotic | Evil | Good | Lawful => self.affects_align(target.align),
+            Positive => target.vitality == Vitality::Undead,
+            Negative => target.vitality == Vitality::Living,
+            _ => true,
+        }
+    }
 }
 
 impl Damage {
-    pub fn eval<R: rand::Rng>(&self, rng: &mut RandState<R>) -> DamageResult {
-        DamageResult {
-            tp: self.tp,
-            magical: self.magical,
-            amount: std::cmp::max(self.amount.eval(rng), 0) as usize,
-            prec_amount: std::cmp::max(self.amount.eval(rng), 0) as usize,
+    pub fn eval<R: Roller>(&self, rng: &mut RandState<R>) -> DamageResult {
+        self.roll(rng).result
+    }
+
+    pub fn roll<R: Roller>(&self, rng: &mut RandState<R>) -> DamageRoll {
+        let amount = self.amount.roll(rng);
+        let prec_amount = self.prec_amount.as_ref().map(|p| p.roll(rng));
+        DamageRoll {
+            result: DamageResult {
+                tp: self.tp,
+                magical: self.magical,
+                material: self.material,
+                amount: std::cmp::max(amount.total, 0) as usize,
+                prec_amount: prec_amount.as_ref().map_or(0, |r| std::cmp::max(r.total, 0) as usize),
+            },
+            amount,
+            prec_amount,
+        }
+    }
+
+    // The damage dealt on a critical hit: everything doubled, then any deadly or fatal dice added
+    // on top. The result is an ordinary Damage, so its transcript and distribution come for free.
+    pub fn critical(&self, traits: CritTraits) -> Damage {
+        let base = match traits.fatal {
+            Some(faces) => self.amount.clone().with_faces(faces),
+            None => self.amount.clone(),
+        };
+        let mut amount = RandValue::Product(vec![base, RandValue::Const(2)]);
+        if let Some(faces) = traits.deadly {
+            // One deadly die, two with greater striking (3 weapon dice), three with major (4).
+            let times = match self.amount.dice_count() {
+                0 ..= 2 => 1,
+                n => n - 1,
+            };
+            amount = amount + RandValue::Die { faces, times };
         }
+        if let Some(faces) = traits.fatal {
+            amount = amount + RandValue::Die { faces, times: 1 };
+        }
+        Damage {
+            amount,
+            prec_amount: self.prec_amount.as_ref().map(|p| RandValue::Product(vec![p.clone(), RandValue::Const(2)])),
+            ..self.clone()
+        }
+    }
+
+    // Total damage before modifiers, with each part floored at zero as eval() does.
+    pub fn distribution(&self) -> Distribution {
+        let amount = self.amount.distribution().map(|i| std::cmp::max(i, 0));
+        match &self.prec_amount {
+            Some(prec) => amount.convolve(&prec.distribution().map(|i| std::cmp::max(i, 0))),
+            None => amount,
+        }
+    }
+
+    pub fn expected(&self) -> f64 {
+        self.distribution().mean()
+    }
+}
+
+impl DamagePacket {
+    pub fn new(components: Vec<Damage>) -> Self {
+        Self { components, persistent: Vec::new(), splash: None }
+    }
+
+    pub fn with_persistent(mut self, dmg: Damage) -> Self {
+        self.persistent.push(dmg);
+        self
+    }
+
+    pub fn with_splash(mut self, dmg: Damage) -> Self {
+        self.splash = Some(Splash { damage: dmg });
+        self
+    }
+
+    pub fn eval<R: Roller>(&self, rng: &mut RandState<R>) -> Vec<DamageResult> {
+        self.components.iter().map(|c| c.eval(rng)).collect()
+    }
+
+    pub fn roll<R: Roller>(&self, rng: &mut RandState<R>) -> Vec<DamageRoll> {
+        self.components.iter().map(|c| c.roll(rng)).collect()
+    }
+
+    // Critical hits double every component but splash; deadly and fatal only affect the first,
+    // which is the weapon's own damage.
+    pub fn critical(&self, traits: CritTraits) -> DamagePacket {
+        DamagePacket {
+            components: self.components.iter()
+                .enumerate()
+                .map(|(idx, c)| c.critical(if idx == 0 { traits } else { Default::default() }))
+                .collect(),
+            persistent: self.persistent.iter().map(|p| p.critical(Default::default())).collect(),
+            splash: self.splash.clone(),
+        }
+    }
+
+    pub fn expected(&self) -> f64 {
+        self.components.iter().map(|c| c.expected()).sum()
+    }
+}
+
+impl Splash {
+    // The squares around a creature of the given footprint whose corner is at `target`, not
+    // counting its own.
+    pub fn squares(target: Location, space: Squares) -> Vec<Location> {
+        let side = std::cmp::max(space.0, 1) as isize;
+        (-1 ..= side)
+            .flat_map(|dy| (-1 ..= side).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| !(0 .. side).contains(&dx) || !(0 .. side).contains(&dy))
+            .map(|(dx, dy)| target.offset(dx, dy))
+            .collect()
+    }
+}
+
+impl Spec {
+    pub fn matches(self, dmg: &DamageResult) -> bool {
+        match self {
+            Spec::Type(tp) => dmg.tp == tp,
+            Spec::Kind(kind) => dmg.tp.kind() == kind,
+            Spec::Material(m) => dmg.material == Some(m),
+            Spec::Magical => dmg.magical,
+            Spec::NonMagical => !dmg.magical,
+            Spec::Precision => dmg.prec_amount > 0,
+            Spec::All => true,
+        }
+    }
+}
+
+impl Resistance {
+    pub fn new(amount: usize) -> Self {
+        Self { amount, except: Vec::new() }
+    }
+
+    pub fn except(mut self, spec: Spec) -> Self {
+        self.except.push(spec);
+        self
+    }
+
+    pub fn applies(&self, dmg: &DamageResult) -> bool {
+        !self.except.iter().any(|&s| s.matches(dmg))
     }
 }
 
 impl Modifiers {
-    fn test(&self, spec: Spec) -> ModTest {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn test(&self, spec: Spec, dmg: &DamageResult) -> ModTest {
         ModTest {
-            resistance: self.resistances.get(&spec).map(|&x| x),
-            weakness: self.weaknesses.get(&spec).map(|&x| x),
+            resistance: self.resistances.get(&spec).filter(|r| r.applies(dmg)).map(|r| r.amount),
+            weakness: self.weaknesses.get(&spec).copied(),
             immunity: self.immunities.contains(&spec),
         }
     }
 
-    fn apply(&self, dmg: DamageResult) -> Option<DamageResult> {
-        let mtype = self.test(Spec::Type(dmg.tp));
-        let mkind = self.test(Spec::Kind(dmg.tp.kind()));
-        let mnonmag = if dmg.magical { Default::default() } else { self.test(Spec::NonMagical) };
-        let mall = self.test(Spec::All);
+    fn test_all(&self, dmg: &DamageResult) -> [ModTest; 5] {
+        [
+            self.test(Spec::Type(dmg.tp), dmg),
+            self.test(Spec::Kind(dmg.tp.kind()), dmg),
+            match dmg.material {
+                Some(m) => self.test(Spec::Material(m), dmg),
+                None => Default::default(),
+            },
+            if dmg.magical { Default::default() } else { self.test(Spec::NonMagical, dmg) },
+            self.test(Spec::All, dmg),
+        ]
+    }
 
-        let results = &[mtype, mkind, mnonmag, mall];
-        if results.iter().any(|t| t.immunity) {
-            return None;
+    pub fn apply(&self, dmg: DamageResult, target: &Target) -> Option<DamageResult> {
+        let total = self.apply_all(&[dmg], target).total;
+        if total == 0 {
+            None
+        } else {
+            Some(DamageResult { amount: total, prec_amount: 0, ..dmg })
         }
-        let weakness: usize = results.iter().filter_map(|x| x.weakness).sum();
-        let resistance: usize = results.iter().filter_map(|x| x.resistance).sum();
-        let mut damage = dmg.amount;
+    }
 
-        let mprec = self.test(Spec::Precision);
-        let prec_damage = if mprec.immunity {
-            0
-        } else {
-            (dmg.prec_amount + mprec.weakness.unwrap_or(0)).saturating_sub(mprec.resistance.unwrap_or(0))
+    // Applies these modifiers to every component of one packet at once, as PF2e does: immunity (or
+    // damage that can't affect the target at all) removes individual components, only the single
+    // highest applicable weakness is added (once, to the type that triggered it), and resistance
+    // is applied separately to each damage type using the highest resistance that applies to it.
+    // Components of the same type and material are pooled first; a pool counts as magical if any
+    // of its components is.
+    pub fn apply_all(&self, dmgs: &[DamageResult], target: &Target) -> PacketResult {
+        let mut pools: Vec<DamageResult> = Vec::new();
+        let mut weakness: Option<(DamageType, Option<Material>, usize)> = None;
+
+        for dmg in dmgs {
+            if !dmg.tp.affects(target) {
+                continue;
+            }
+            let tests = self.test_all(dmg);
+            if tests.iter().any(|t| t.immunity) {
+                continue;
+            }
+            let mprec = self.test(Spec::Precision, dmg);
+            let prec_amount = if mprec.immunity { 0 } else { dmg.prec_amount };
+            if dmg.amount + prec_amount == 0 {
+                continue;
+            }
+
+            let mut weak = tests.iter().filter_map(|t| t.weakness).max();
+            if prec_amount > 0 {
+                weak = std::cmp::max(weak, mprec.weakness);
+            }
+            match (weak, weakness) {
+                (Some(w), Some((_, _, cur))) if w <= cur => (),
+                (Some(w), _) => weakness = Some((dmg.tp, dmg.material, w)),
+                (None, _) => (),
+            }
+
+            match pools.iter_mut().find(|p| p.tp == dmg.tp && p.material == dmg.material) {
+                Some(pool) => {
+                    pool.amount += dmg.amount;
+                    pool.prec_amount += prec_amount;
+                    pool.magical |= dmg.magical;
+                },
+                None => pools.push(DamageResult { prec_amount, ..*dmg }),
+            }
+        }
+
+        let parts: Vec<DamageResult> = pools.into_iter()
+            .map(|pool| {
+                let resistance = self.test_all(&pool)
+                    .iter()
+                    .filter_map(|t| t.resistance)
+                    .max()
+                    .unwrap_or(0);
+                let prec_resistance = self.test(Spec::Precision, &pool).resistance.unwrap_or(0);
+                let prec_damage = pool.prec_amount.saturating_sub(prec_resistance);
+                let weak = match weakness {
+                    Some((tp, m, w)) if tp == pool.tp && m == pool.material => w,
+                    _ => 0,
+                };
+                let amount = (pool.amount + prec_damage + weak).saturating_sub(resistance);
+                DamageResult { amount, prec_amount: 0, ..pool }
+            })
+            .collect();
+
+        PacketResult {
+            total: parts.iter().map(|p| p.amount).sum(),
+            parts,
+            weakness: weakness.map(|(_, _, w)| w),
+        }
+    }
+
+    // Whether these modifiers ignore this damage outright, e.g. before attaching it as persistent.
+    pub fn immune(&self, dmg: &Damage) -> bool {
+        let probe = DamageResult {
+            tp: dmg.tp,
+            magical: dmg.magical,
+            material: dmg.material,
+            amount: 0,
+            prec_amount: 0,
         };
-        damage += prec_damage;
+        self.test_all(&probe).iter().any(|t| t.immunity)
+    }
+}
 
-        damage = (damage + weakness).saturating_sub(resistance);
-        if damage == 0 {
-            None
-        } else {
-            Some(DamageResult { amount: damage, prec_amount: 0, ..dmg })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target() -> Target {
+        Target {
+            align: Alignment { ge: GoodEvil::Neutral, lc: LawChaos::Neutral },
+            vitality: Vitality::Living,
         }
     }
+
+    fn piercing(amount: &str, prec: Option<&str>) -> Damage {
+        Damage {
+            tp: DamageType::Piercing,
+            magical: false,
+            material: None,
+            amount: amount.parse().unwrap(),
+            prec_amount: prec.map(|p| p.parse().unwrap()),
+        }
+    }
+
+    fn applied(dmg: &Damage, rolls: Vec<usize>, mods: &Modifiers) -> usize {
+        let mut rng = RandState::scripted(rolls);
+        let result = dmg.eval(&mut rng);
+        assert_eq!(rng.inner().remaining(), 0);
+        mods.apply(result, &target()).map_or(0, |r| r.amount)
+    }
+
+    #[test]
+    fn precision() {
+        let dmg = piercing("1d8+4", Some("1d6"));
+        assert_eq!(applied(&dmg, vec![5, 3], &Modifiers::new()), 12);
+
+        let mut mods = Modifiers::new();
+        mods.immunities.insert(Spec::Precision);
+        assert_eq!(applied(&dmg, vec![5, 3], &mods), 9);
+
+        let mut mods = Modifiers::new();
+        mods.resistances.insert(Spec::Precision, Resistance::new(2));
+        assert_eq!(applied(&dmg, vec![5, 3], &mods), 10);
+
+        let mut mods = Modifiers::new();
+        mods.weaknesses.insert(Spec::Precision, 4);
+        assert_eq!(applied(&dmg, vec![5, 3], &mods), 16);
+        // Precision weakness needs precision damage to trigger.
+        assert_eq!(applied(&piercing("1d8+4", None), vec![5], &mods), 9);
+    }
+
+    #[test]
+    fn critical() {
+        let crit = piercing("1d8+4", Some("1d6")).critical(Default::default());
+        assert_eq!(applied(&crit, vec![5, 3], &Modifiers::new()), 24);
+
+        // Resistance applies once, to the doubled total.
+        let mut mods = Modifiers::new();
+        mods.resistances.insert(Spec::Type(DamageType::Piercing), Resistance::new(5));
+        assert_eq!(applied(&crit, vec![5, 3], &mods), 19);
+    }
+
+    #[test]
+    fn deadly() {
+        let traits = CritTraits { deadly: Some(10), fatal: None };
+        assert_eq!(applied(&piercing("1d8+4", None).critical(traits), vec![5, 7], &Modifiers::new()), 25);
+        assert_eq!(applied(&piercing("2d8+4", None).critical(traits), vec![5, 1, 7], &Modifiers::new()), 27);
+        assert_eq!(applied(&piercing("3d8+4", None).critical(traits), vec![5, 1, 2, 7, 10], &Modifiers::new()), 41);
+        assert_eq!(applied(&piercing("4d8+4", None).critical(traits), vec![1, 1, 1, 1, 1, 1, 1], &Modifiers::new()), 19);
+    }
+
+    #[test]
+    fn fatal() {
+        let traits = CritTraits { deadly: None, fatal: Some(12) };
+        // A 12 is impossible on the d8 the weapon normally rolls, so the script only gets through
+        // if the die has been resized.
+        assert_eq!(applied(&piercing("1d8+4", None).critical(traits), vec![12, 7], &Modifiers::new()), 39);
+        assert_eq!(applied(&piercing("2d8", None).critical(traits), vec![12, 11, 1], &Modifiers::new()), 47);
+        // Fatal does nothing on an ordinary hit.
+        assert_eq!(applied(&piercing("1d8+4", None), vec![8], &Modifiers::new()), 12);
+    }
+
+    #[test]
+    fn splash() {
+        let fire = Damage {
+            tp: DamageType::Fire,
+            magical: false,
+            material: None,
+            amount: "3".parse().unwrap(),
+            prec_amount: None,
+        };
+        let packet = DamagePacket::new(vec![piercing("1d8", None)]).with_splash(fire);
+        let crit = packet.critical(Default::default());
+        let splash = &crit.splash.as_ref().unwrap().damage;
+        assert_eq!(applied(splash, vec![], &Modifiers::new()), 3);
+
+        let mut mods = Modifiers::new();
+        mods.resistances.insert(Spec::Type(DamageType::Fire), Resistance::new(2));
+        assert_eq!(applied(splash, vec![], &mods), 1);
+        mods.immunities.insert(Spec::Type(DamageType::Fire));
+        assert_eq!(applied(splash, vec![], &mods), 0);
+
+        let corner = Location(util::V2i::new(0, 0));
+        let around = Splash::squares(corner, Squares(1));
+        assert_eq!(around.len(), 8);
+        assert!(!around.contains(&corner));
+        let around = Splash::squares(corner, Squares(2));
+        assert_eq!(around.len(), 12);
+        assert!(!around.contains(&corner.offset(1, 1)));
+        assert!(around.contains(&corner.offset(2, 2)));
+    }
 }
@@ -0,0 +1,291 @@
+// Typed bonuses and penalties. Of each type but untyped, only the highest bonus and the worst
+// penalty apply to any one statistic; untyped ones all stack.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{Ability, Save, Skill};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum BonusType {
+    Circumstance,
+    Status,
+    Item,
+    Untyped,
+}
+
+// A statistic a modifier can end up on.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Stat {
+    // Attack rolls, by the ability they use.
+    Attack(Ability),
+    // Damage rolls that add the given ability's modifier, e.g. Strength for melee Strikes.
+    Damage(Ability),
+    Perception,
+    Save(Save),
+    Skill(Skill),
+    Ac,
+    // Any other DC of the creature's, by the ability it's keyed to: its Will DC against
+    // Demoralize is Dc(Some(Wis)). Class and spell DCs and the like are Dc(None).
+    Dc(Option<Ability>),
+}
+
+// Which statistics a modifier applies to.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Selector {
+    // Every check and DC, AC included, but not damage.
+    All,
+    Checks,
+    Dcs,
+    Saves,
+    Skills,
+    Attacks,
+    // Checks and DCs based on the given ability, and damage that adds it.
+    Ability(Ability),
+    Stat(Stat),
+}
+
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+pub struct Modifier {
+    // Where it came from, e.g. "frightened" or "+1 striking rune".
+    pub name: String,
+    pub tp: BonusType,
+    pub value: isize,
+    pub selector: Selector,
+}
+
+#[derive(Debug,Clone,Default,PartialEq,Eq)]
+pub struct ModifierSet {
+    mods: Vec<Modifier>,
+}
+
+// The outcome of stacking everything that applies to one statistic.
+#[derive(Debug,Clone,Default,PartialEq,Eq)]
+pub struct Resolved {
+    pub total: isize,
+    pub applied: Vec<Modifier>,
+    pub suppressed: Vec<Modifier>,
+}
+
+impl Stat {
+    pub fn is_dc(self) -> bool {
+        matches!(self, Stat::Ac | Stat::Dc(_))
+    }
+
+    // The DC that goes with this statistic, as when rolling against a target's Will DC.
+    pub fn dc(self) -> Stat {
+        match self {
+            Stat::Attack(_) | Stat::Ac => Stat::Ac,
+            Stat::Dc(ab) => Stat::Dc(ab),
+            stat => Stat::Dc(stat.ability()),
+        }
+    }
+
+    pub fn is_damage(self) -> bool {
+        matches!(self, Stat::Damage(_))
+    }
+
+    // The ability this is keyed to, if known.
+    pub fn ability(self) -> Option<Ability> {
+        match self {
+            Stat::Attack(ab) | Stat::Damage(ab) => Some(ab),
+            Stat::Perception => Some(Ability::Wis),
+            Stat::Save(save) => Some(save.ability()),
+            Stat::Skill(skill) => Some(skill.ability()),
+            Stat::Ac => Some(Ability::Dex),
+            Stat::Dc(ab) => ab,
+        }
+    }
+}
+
+impl Selector {
+    pub fn selects(self, stat: Stat) -> bool {
+        match self {
+            Selector::All => !stat.is_damage(),
+            Selector::Checks => !stat.is_dc() && !stat.is_damage(),
+            Selector::Dcs => stat.is_dc(),
+            Selector::Saves => matches!(stat, Stat::Save(_)),
+            Selector::Skills => matches!(stat, Stat::Skill(_)),
+            Selector::Attacks => matches!(stat, Stat::Attack(_)),
+            Selector::Ability(ab) => stat.ability() == Some(ab),
+            Selector::Stat(s) => s == stat,
+        }
+    }
+}
+
+impl Modifier {
+    pub fn new(name: &str, tp: BonusType, value: isize, selector: Selector) -> Self {
+        Self { name: name.to_string(), tp, value, selector }
+    }
+
+    pub fn circumstance(name: &str, value: isize, selector: Selector) -> Self {
+        Self::new(name, BonusType::Circumstance, value, selector)
+    }
+
+    pub fn status(name: &str, value: isize, selector: Selector) -> Self {
+        Self::new(name, BonusType::Status, value, selector)
+    }
+
+    pub fn item(name: &str, value: isize, selector: Selector) -> Self {
+        Self::new(name, BonusType::Item, value, selector)
+    }
+
+    pub fn untyped(name: &str, value: isize, selector: Selector) -> Self {
+        Self::new(name, BonusType::Untyped, value, selector)
+    }
+}
+
+impl ModifierSet {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn push(&mut self, m: Modifier) {
+        self.mods.push(m);
+    }
+
+    pub fn with(mut self, m: Modifier) -> Self {
+        self.push(m);
+        self
+    }
+
+    pub fn extend(&mut self, other: &ModifierSet) {
+        self.mods.extend(other.mods.iter().cloned());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Modifier> {
+        self.mods.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mods.is_empty()
+    }
+
+    pub fn resolve(&self, stat: Stat) -> Resolved {
+        let relevant: Vec<&Modifier> = self.mods.iter()
+            .filter(|m| m.value != 0 && m.selector.selects(stat))
+            .collect();
+
+        // The strongest bonus and penalty of each type; the first one wins a tie.
+        let mut best: HashMap<(BonusType, bool), usize> = HashMap::new();
+        for (idx, m) in relevant.iter().enumerate() {
+            if m.tp == BonusType::Untyped {
+                continue;
+            }
+            let slot = best.entry((m.tp, m.value > 0)).or_insert(idx);
+            if m.value.abs() > relevant[*slot].value.abs() {
+                *slot = idx;
+            }
+        }
+
+        let mut res = Resolved::default();
+        for (idx, m) in relevant.into_iter().enumerate() {
+            if m.tp == BonusType::Untyped || best[&(m.tp, m.value > 0)] == idx {
+                res.total += m.value;
+                res.applied.push(m.clone());
+            } else {
+                res.suppressed.push(m.clone());
+            }
+        }
+        res
+    }
+
+    pub fn total(&self, stat: Stat) -> isize {
+        self.resolve(stat).total
+    }
+}
+
+impl fmt::Display for BonusType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            BonusType::Circumstance => "circumstance",
+            BonusType::Status => "status",
+            BonusType::Item => "item",
+            BonusType::Untyped => "untyped",
+        })
+    }
+}
+
+// e.g. "-2 status (sickened)"
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:+} {} ({})", self.value, self.tp, self.name)
+    }
+}
+
+// e.g. "-2 = -2 status (sickened); suppressed -1 status (frightened)"
+impl fmt::Display for Resolved {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:+}", self.total)?;
+        for (idx, m) in self.applied.iter().enumerate() {
+            write!(f, "{}{}", if idx == 0 { " = " } else { ", " }, m)?;
+        }
+        for (idx, m) in self.suppressed.iter().enumerate() {
+            write!(f, "{}{}", if idx == 0 { "; suppressed " } else { ", " }, m)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WILL: Stat = Stat::Save(Save::Will);
+
+    #[test]
+    fn typed_best_and_worst() {
+        let mods = ModifierSet::new()
+            .with(Modifier::status("heroism", 1, Selector::All))
+            .with(Modifier::status("bless", 2, Selector::All))
+            .with(Modifier::status("frightened", -1, Selector::All))
+            .with(Modifier::status("sickened", -2, Selector::All))
+            .with(Modifier::circumstance("aid", 1, Selector::All))
+            .with(Modifier::circumstance("cover", 2, Selector::Stat(Stat::Ac)));
+        let res = mods.resolve(WILL);
+        // +2 status and -2 status both apply, as does the lone circumstance bonus.
+        assert_eq!(res.total, 1);
+        let applied: Vec<&str> = res.applied.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(applied, vec!["bless", "sickened", "aid"]);
+        let suppressed: Vec<&str> = res.suppressed.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(suppressed, vec!["heroism", "frightened"]);
+
+        assert_eq!(mods.total(Stat::Ac), 2);
+    }
+
+    #[test]
+    fn untyped_stack() {
+        let mods = ModifierSet::new()
+            .with(Modifier::untyped("multiple attack", -5, Selector::Attacks))
+            .with(Modifier::untyped("range", -2, Selector::Attacks))
+            .with(Modifier::untyped("range", -2, Selector::Attacks))
+            .with(Modifier::status("frightened", -1, Selector::All));
+        let res = mods.resolve(Stat::Attack(Ability::Str));
+        assert_eq!(res.total, -10);
+        assert!(res.suppressed.is_empty());
+    }
+
+    #[test]
+    fn ties_keep_the_first() {
+        let mods = ModifierSet::new()
+            .with(Modifier::item("+1 armor", 1, Selector::Stat(Stat::Ac)))
+            .with(Modifier::item("bracers", 1, Selector::Stat(Stat::Ac)));
+        let res = mods.resolve(Stat::Ac);
+        assert_eq!(res.total, 1);
+        assert_eq!(res.applied[0].name, "+1 armor");
+        assert_eq!(res.suppressed[0].name, "bracers");
+    }
+
+    #[test]
+    fn selectors() {
+        let mods = ModifierSet::new()
+            .with(Modifier::status("clumsy", -1, Selector::Ability(Ability::Dex)))
+            .with(Modifier::status("sickened", -1, Selector::All))
+            .with(Modifier::item("striking", 2, Selector::Stat(Stat::Damage(Ability::Str))));
+        assert_eq!(mods.total(Stat::Ac), -1);
+        assert_eq!(mods.total(Stat::Dc(Some(Ability::Dex))), -1);
+        assert_eq!(mods.total(Stat::Dc(None)), -1);
+        assert_eq!(mods.total(Stat::Damage(Ability::Str)), 2);
+        assert_eq!(mods.total(Stat::Attack(Ability::Str)), -1);
+    }
+}
@@ -1,6 +1,9 @@
 use crate::world::space::*;
 
-#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Size {
     Tiny,
     Small,
@@ -21,4 +24,11 @@ impl Size {
             Gargantuan => 4,  // XXX "or more"
         })
     }
+
+    // The squares covered by a creature of this size whose corner is at `loc`. Tiny creatures
+    // still take up the square they're in.
+    pub fn footprint(self, loc: Location) -> Vec<Location> {
+        let side = std::cmp::max(self.space().0, 1) as isize;
+        (0 .. side).flat_map(|dy| (0 .. side).map(move |dx| loc.offset(dx, dy))).collect()
+    }
 }
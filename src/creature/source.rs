@@ -0,0 +1,117 @@
+// Where an effect came from, so that it can be dispelled, counteracted or resisted by whatever
+// the source was rather than by the condition it caused.
+
+use serde::{Serialize, Deserialize};
+
+use super::{CID, Creature, Degree, Stat, Trait};
+use crate::world::affliction::AffRef;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EffectTrait {
+    Curse,
+    Death,
+    Disease,
+    Emotion,
+    Fear,
+    Magical,
+    Mental,
+    Poison,
+    Sleep,
+}
+
+// Nothing is known about an effect's source by default; such effects can't be counteracted, and
+// no immunity applies to them.
+#[derive(Debug,Clone,Default,PartialEq,Eq)]
+pub struct Source {
+    pub cid: Option<CID>,
+    // The spell, ability or item, e.g. "fear" or "Demoralize".
+    pub name: String,
+    // The counteract rank: the spell's rank, or half the level of anything else, rounded up.
+    pub rank: Option<usize>,
+    // What a counteract check has to beat.
+    pub dc: Option<isize>,
+    pub traits: Vec<EffectTrait>,
+    // The affliction behind a stage's effects, and behind its progress tracker.
+    pub affliction: Option<AffRef>,
+}
+
+impl Source {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), ..Default::default() }
+    }
+
+    pub fn by(mut self, cid: CID) -> Self {
+        self.cid = Some(cid);
+        self
+    }
+
+    pub fn rank(mut self, rank: usize) -> Self {
+        self.rank = Some(rank);
+        self
+    }
+
+    pub fn dc(mut self, dc: isize) -> Self {
+        self.dc = Some(dc);
+        self
+    }
+
+    pub fn with_trait(mut self, tr: EffectTrait) -> Self {
+        if !self.traits.contains(&tr) {
+            self.traits.push(tr);
+        }
+        self
+    }
+
+    pub fn has_trait(&self, tr: EffectTrait) -> bool {
+        self.traits.contains(&tr)
+    }
+
+    // The counteract rank of a non-spell effect of the given level.
+    pub fn rank_of_level(level: isize) -> usize {
+        std::cmp::max((level + 1) / 2, 0) as usize
+    }
+}
+
+// An attempt to counteract, e.g. dispel magic or remove fear, rolled with `modifier` on `stat`:
+// usually a spell attack, or a skill for things like Treat Poison. The conditions of the creature
+// it's `by`, if any, apply to the check.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Counteract {
+    pub name: String,
+    pub by: Option<CID>,
+    pub stat: Stat,
+    pub modifier: isize,
+    pub rank: usize,
+}
+
+impl Counteract {
+    pub fn new(name: &str, stat: Stat, modifier: isize, rank: usize) -> Self {
+        Self { name: name.to_string(), by: None, stat, modifier, rank }
+    }
+
+    pub fn by(mut self, cid: CID) -> Self {
+        self.by = Some(cid);
+        self
+    }
+
+    // How high a rank this can counteract at each degree of success.
+    pub fn succeeds(&self, degree: Degree, target: usize) -> bool {
+        match degree {
+            Degree::CriticalSuccess => target <= self.rank + 3,
+            Degree::Success => target <= self.rank + 1,
+            Degree::Failure => target < self.rank,
+            Degree::CriticalFailure => false,
+        }
+    }
+}
+
+impl Creature {
+    // Mindless creatures are immune to mental effects on top of anything in their stat block.
+    pub fn immune_to(&self, source: &Source) -> bool {
+        source.traits.iter().any(|tr| {
+            self.effect_immunities.contains(tr)
+                || (*tr == EffectTrait::Mental && self.has_trait(&Trait::Mindless))
+        })
+    }
+}
@@ -0,0 +1,141 @@
+use super::ability::Ability;
+use crate::world::space::Feet;
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Proficiency {
+    Untrained,
+    Trained,
+    Expert,
+    Master,
+    Legendary,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Save {
+    Fortitude,
+    Reflex,
+    Will,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Skill {
+    Acrobatics,
+    Arcana,
+    Athletics,
+    Crafting,
+    Deception,
+    Diplomacy,
+    Intimidation,
+    Medicine,
+    Nature,
+    Occultism,
+    Performance,
+    Religion,
+    Society,
+    Stealth,
+    Survival,
+    Thievery,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpeedType {
+    Land,
+    Burrow,
+    Climb,
+    Fly,
+    Swim,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Sense {
+    LowLightVision,
+    Darkvision,
+    GreaterDarkvision,
+    Scent { range: Feet },
+    Tremorsense { range: Feet },
+}
+
+// Worn armor, or a creature's natural equivalent.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct Armor {
+    pub prof: Proficiency,
+    pub item_bonus: isize,
+    pub dex_cap: Option<isize>,
+}
+
+impl Proficiency {
+    // Untrained adds nothing; every rank above it adds the creature's level plus 2 per rank.
+    pub fn bonus(self, level: isize) -> isize {
+        use Proficiency::*;
+
+        match self {
+            Untrained => 0,
+            Trained => level + 2,
+            Expert => level + 4,
+            Master => level + 6,
+            Legendary => level + 8,
+        }
+    }
+}
+
+impl Save {
+    pub fn ability(self) -> Ability {
+        match self {
+            Save::Fortitude => Ability::Con,
+            Save::Reflex => Ability::Dex,
+            Save::Will => Ability::Wis,
+        }
+    }
+}
+
+impl Skill {
+    pub fn ability(self) -> Ability {
+        use Skill::*;
+
+        match self {
+            Acrobatics | Stealth | Thievery => Ability::Dex,
+            Athletics => Ability::Str,
+            Arcana | Crafting | Occultism | Society => Ability::Int,
+            Medicine | Nature | Religion | Survival => Ability::Wis,
+            Deception | Diplomacy | Intimidation | Performance => Ability::Cha,
+        }
+    }
+}
+
+// Parses e.g. "darkvision", "low-light-vision" or "scent 30".
+impl std::str::FromStr for Sense {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut words = s.split_whitespace();
+        let name = words.next().unwrap_or("").to_lowercase();
+        let range = match words.next() {
+            Some(w) => Some(w.parse().map(Feet).map_err(|_| format!("bad range {:?} in sense {:?}", w, s))?),
+            None => None,
+        };
+        if words.next().is_some() {
+            return Err(format!("unknown sense {:?}", s));
+        }
+        match (name.as_str(), range) {
+            ("low-light-vision", None) => Ok(Sense::LowLightVision),
+            ("darkvision", None) => Ok(Sense::Darkvision),
+            ("greater-darkvision", None) => Ok(Sense::GreaterDarkvision),
+            ("scent", Some(range)) => Ok(Sense::Scent { range }),
+            ("tremorsense", Some(range)) => Ok(Sense::Tremorsense { range }),
+            _ => Err(format!("unknown sense {:?}", s)),
+        }
+    }
+}
+
+impl Default for Armor {
+    // Unarmored, but trained in it as nearly everything is.
+    fn default() -> Self {
+        Self { prof: Proficiency::Trained, item_bonus: 0, dex_cap: None }
+    }
+}
@@ -1,15 +1,26 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
+use std::mem;
 use std::rc::Rc;
 
 use super::{
     CID,
+    Ability,
     Damage,
     DamageResult,
+    DamageRoll,
+    Check,
+    CheckResult,
+    Degree,
+    EffectTrait,
+    Modifier,
+    ModifierSet,
+    Selector,
+    Source,
+    Stat,
 };
-use crate::world::{World, time::*};
-use crate::rng::{RandValue, RandState};
-use rand::Rng;
+use crate::world::{World, affliction::AffRef, space::Feet, time::*};
+use crate::rng::{RandValue, RandState, Roll, Roller};
 
 #[derive(Debug,Clone)]
 pub struct DmgRef(pub Rc<Damage>);
@@ -35,31 +46,159 @@ pub enum Effect {
     Sickened { level: usize, },
     Slowed { level: usize, },
     Stunned { level: usize, },
+    // Stunned for a duration instead: no actions at all until it ends.
+    Stunned_For { until: Lasts, },
     Dying { level: usize, },
     Doomed { level: usize, },
     Wounded { level: usize, },
-    Unconscious { until: Option<Round>, },
-    Restrained { until: Option<Round>, },
-    Immobilized { until: Option<Round>, },
-    Paralyzed { until: Option<Round>, },
-    Flat_Footed { until: Option<Round>, },
-    Prone { until: Option<Round>, },
-    Blinded { until: Option<Round>, },
+    Unconscious { until: Lasts, },
+    Restrained { until: Lasts, },
+    Immobilized { until: Lasts, },
+    Paralyzed { until: Lasts, },
+    Flat_Footed { until: Lasts, },
+    Prone { until: Lasts, },
+    Blinded { until: Lasts, },
+    Clumsy { level: usize, },
+    Drained { level: usize, },
+    Enfeebled { level: usize, },
+    Stupefied { level: usize, },
+    Fatigued { until: Lasts, },
+    Confused { until: Lasts, },
+    Controlled { by: CID, },
+    Dazzled { until: Lasts, },
+    Deafened { until: Lasts, },
+    Fascinated { until: Lasts, },
+    Fleeing { until: Lasts, },
+    Concealed { until: Lasts, },
+    Invisible { until: Lasts, },
+    // Hidden and undetected are relative to one observer each.
+    Hidden { from: CID, },
+    Undetected { from: CID, },
+    // One extra action a turn, usable only for activities of the kinds in `only` (see
+    // Activity::kind).
+    Quickened { until: Lasts, only: Vec<String>, },
+    Encumbered,
+    Petrified { until: Lasts, },
     Grappled { holder: CID, },
     Grappling { holding: CID, },
     DemoralizedImmune { to: CID, until: Round, },
     PersistentDamage { dmg: DmgRef },
+    // Progress through a poison or disease; see world::affliction. `stage` counts from 1, and
+    // hasn't taken effect yet while `started` is false, during the onset.
+    Afflicted { aff: AffRef, stage: usize, started: bool, next: Round, ends: Option<Round>, streak: usize, },
     // "Virtual": cause other effects to be stored in the Status map:
     Demoralize { level: usize, by: CID, dur: Option<Rounds>, },
     BecomeDying,  // At the default Wounded level
 }
 
+impl Effect {
+    pub fn level(&self) -> Option<usize> {
+        use Effect::*;
+
+        match *self {
+            Frightened { level } | Sickened { level } | Slowed { level } | Stunned { level }
+                | Dying { level } | Doomed { level } | Wounded { level }
+                | Clumsy { level } | Drained { level } | Enfeebled { level } | Stupefied { level } => Some(level),
+            _ => None,
+        }
+    }
+
+    // How long a condition lasts, for those that can wear off by themselves.
+    pub fn lasts(&self) -> Option<Lasts> {
+        use Effect::*;
+
+        match *self {
+            Unconscious { until } | Restrained { until } | Immobilized { until } | Paralyzed { until }
+                | Flat_Footed { until } | Prone { until } | Blinded { until }
+                | Fatigued { until } | Confused { until } | Dazzled { until } | Deafened { until }
+                | Fascinated { until } | Fleeing { until } | Concealed { until } | Invisible { until }
+                | Quickened { until, .. } | Petrified { until } | Stunned_For { until } => Some(until),
+            DemoralizedImmune { until, .. } => Some(Lasts::Until(until)),
+            _ => None,
+        }
+    }
+
+    fn lasts_mut(&mut self) -> Option<&mut Lasts> {
+        use Effect::*;
+
+        match self {
+            Unconscious { until } | Restrained { until } | Immobilized { until } | Paralyzed { until }
+                | Flat_Footed { until } | Prone { until } | Blinded { until }
+                | Fatigued { until } | Confused { until } | Dazzled { until } | Deafened { until }
+                | Fascinated { until } | Fleeing { until } | Concealed { until } | Invisible { until }
+                | Quickened { until, .. } | Petrified { until } | Stunned_For { until } => Some(until),
+            _ => None,
+        }
+    }
+
+    // Called at the `hook` of `turn_of`'s turn (see Lasts::tick()). Also counts down conditions
+    // that drop by one at the end of their holder's turn, like frightened. None once it's over.
+    pub fn tick(mut self, turn_of: CID, own: bool, hook: Hook, now: Round) -> Option<Effect> {
+        use Effect::*;
+
+        let ending = own && hook == Hook::End;
+        match self {
+            Frightened { level } if ending => return if level > 1 { Some(Frightened { level: level - 1 }) } else { None },
+            DemoralizedImmune { until, .. } if ending && until <= now => return None,
+            _ => (),
+        }
+        let going = match self.lasts_mut() {
+            Some(lasts) => lasts.tick(turn_of, own, hook, now),
+            None => true,
+        };
+        if going { Some(self) } else { None }
+    }
+
+    // Traits an effect always has, whatever its source says.
+    pub fn traits(&self) -> &'static [EffectTrait] {
+        match self {
+            Effect::Demoralize { .. } => &[EffectTrait::Emotion, EffectTrait::Fear, EffectTrait::Mental],
+            _ => &[],
+        }
+    }
+
+    // Whether the two are the same condition, and so can't both be stored: grappling, hidden and
+    // undetected are per creature, demoralize immunity per source, persistent damage per damage
+    // type and afflictions per affliction.
+    pub fn same_slot(&self, other: &Effect) -> bool {
+        use Effect::*;
+
+        match (self, other) {
+            (Grappled { holder: a }, Grappled { holder: b }) => a == b,
+            (Grappling { holding: a }, Grappling { holding: b }) => a == b,
+            (Hidden { from: a }, Hidden { from: b }) => a == b,
+            (Undetected { from: a }, Undetected { from: b }) => a == b,
+            (DemoralizedImmune { to: a, .. }, DemoralizedImmune { to: b, .. }) => a == b,
+            (PersistentDamage { dmg: a }, PersistentDamage { dmg: b }) => a.0.tp == b.0.tp,
+            (Afflicted { aff: a, .. }, Afflicted { aff: b, .. }) => a == b,
+            _ => mem::discriminant(self) == mem::discriminant(other),
+        }
+    }
+
+    // Whether this should replace `old` in its slot: higher values, longer durations and more
+    // persistent damage win. Conditions with none of those, like controlled, go to the newer one.
+    pub fn supersedes(&self, old: &Effect) -> bool {
+        if let (Some(new), Some(old)) = (self.level(), old.level()) {
+            return new > old;
+        }
+        // When it isn't clear which ends first, the newer one wins.
+        if let (Some(new), Some(old)) = (self.lasts(), old.lasts()) {
+            return new.outlasts(&old).unwrap_or(true);
+        }
+        match (self, old) {
+            (Effect::PersistentDamage { dmg: new }, Effect::PersistentDamage { dmg: old }) => new.0.expected() > old.0.expected(),
+            _ => self != old,
+        }
+    }
+}
+
 #[derive(Debug,Clone,Default)]
 pub struct Current {
     pub frightened: usize,
     pub sickened: usize,
     pub slowed: usize,
     pub stunned: usize,
+    pub stunned_for: bool,
     pub dying: usize,
     pub wounded: usize,
     pub doomed: usize,
@@ -70,101 +209,327 @@ pub struct Current {
     pub prone: bool,
     pub dead: bool,
     pub flat_footed: bool,
-    pub check_mod: isize,
-    pub save_mod: isize,
-    pub ac_mod: isize,
-    pub dc_mod: isize,
+    pub clumsy: usize,
+    pub drained: usize,
+    pub enfeebled: usize,
+    pub stupefied: usize,
+    pub fatigued: bool,
+    pub confused: bool,
+    pub controlled_by: Option<CID>,
+    pub dazzled: bool,
+    pub deafened: bool,
+    pub fascinated: bool,
+    pub fleeing: bool,
+    pub concealed: bool,
+    pub invisible: bool,
+    pub hidden_from: HashSet<CID>,
+    pub undetected_by: HashSet<CID>,
+    pub encumbered: bool,
+    pub petrified: bool,
+    // The activities the quickened action may be spent on, if quickened.
+    pub quickened: Option<Vec<String>>,
+    pub modifiers: ModifierSet,
     pub speed_mod: f32,
+    // Subtracted from every speed, after speed_mod.
+    pub speed_penalty: Feet,
     pub actions_gained: usize,
 }
 
+#[derive(Debug,Clone)]
+pub struct PersistentRoll {
+    pub damage: DamageRoll,
+    pub flat_check: Roll,
+    pub ended: bool,
+}
+
+#[derive(Debug,Clone)]
 pub struct Status {
-    effects: HashSet<Effect>,
+    effects: HashMap<Effect, Source>,
 }
 
 impl Status {
     pub const DEFAULT_DYING: usize = 4;
     pub const DEFAULT_ACTIONS: usize = 3;
     pub const DEFAULT_PD_DC: isize = 15;
+    pub const RECOVERY_DC: isize = 10;
 
     pub fn new() -> Self {
         Self {
-            effects: HashSet::new(),
+            effects: HashMap::new(),
+        }
+    }
+
+    // Adds an effect as of round `now`, keeping at most one of each condition (see
+    // Effect::same_slot) and expanding virtual effects. Returns whether anything changed.
+    //
+    // Grappled and Grappling only make sense in pairs; World::add_effect() keeps both sides.
+    pub fn add(&mut self, eff: Effect, now: Round) -> bool {
+        self.add_from(eff, Source::default(), now)
+    }
+
+    // As add(), recording where the effect came from. A replaced effect takes its source with it.
+    // Immunities are up to the caller; see World::add_effect_from().
+    pub fn add_from(&mut self, eff: Effect, source: Source, now: Round) -> bool {
+        use Effect::*;
+
+        match eff {
+            BecomeDying => {
+                self.knock_out(false, now);
+                true
+            },
+            Demoralize { level, by, dur } => {
+                if self.is_immune_to_demoralize(by, now) {
+                    return false;
+                }
+                self.add_from(Frightened { level }, source.clone(), now);
+                let until = now + dur.unwrap_or(Rounds(Rounds::TEN_MINUTES));
+                self.add_from(DemoralizedImmune { to: by, until }, source, now);
+                true
+            },
+            eff => {
+                if let Some(old) = self.get(&eff).cloned() {
+                    if !eff.supersedes(&old) {
+                        return false;
+                    }
+                    self.effects.remove(&old);
+                }
+                self.effects.insert(eff, source);
+                true
+            },
         }
     }
 
-    pub fn before_turn<R: Rng>(&mut self, world: &mut World<R>) -> Current {
+    pub fn remove(&mut self, eff: &Effect) -> bool {
+        self.effects.remove(eff).is_some()
+    }
+
+    // Removes and returns everything matching.
+    pub fn remove_where<F: FnMut(&Effect) -> bool>(&mut self, mut pred: F) -> Vec<Effect> {
+        self.remove_sourced_where(|eff, _| pred(eff)).into_iter().map(|(eff, _)| eff).collect()
+    }
+
+    // As remove_where(), by source as well.
+    pub fn remove_sourced_where<F: FnMut(&Effect, &Source) -> bool>(&mut self, mut pred: F) -> Vec<(Effect, Source)> {
+        let (gone, kept) = mem::take(&mut self.effects).into_iter().partition(|(eff, src)| pred(eff, src));
+        self.effects = kept;
+        gone.into_iter().collect()
+    }
+
+    pub fn has(&self, eff: &Effect) -> bool {
+        self.effects.contains_key(eff)
+    }
+
+    // The stored effect occupying the same slot as `eff`, at whatever level or duration.
+    pub fn get(&self, eff: &Effect) -> Option<&Effect> {
+        self.effects.keys().find(|old| old.same_slot(eff))
+    }
+
+    pub fn source(&self, eff: &Effect) -> Option<&Source> {
+        self.effects.get(eff)
+    }
+
+    pub fn effects(&self) -> impl Iterator<Item = &Effect> {
+        self.effects.keys()
+    }
+
+    pub fn sourced(&self) -> impl Iterator<Item = (&Effect, &Source)> {
+        self.effects.iter()
+    }
+
+    pub fn is_immune_to_demoralize(&self, by: CID, now: Round) -> bool {
+        self.effects.keys().any(|eff| matches!(eff, Effect::DemoralizedImmune { to, until } if *to == by && *until > now))
+    }
+
+    fn level(&self, get: fn(&Effect) -> Option<usize>) -> usize {
+        self.effects.keys().filter_map(get).max().unwrap_or(0)
+    }
+
+    // Replaces whichever of a valued condition is present; a level of 0 just removes it.
+    fn set_level(&mut self, get: fn(&Effect) -> Option<usize>, make: fn(usize) -> Effect, level: usize) {
+        let source = self.effects.iter().find(|(eff, _)| get(eff).is_some()).map(|(_, src)| src.clone());
+        self.effects.retain(|eff, _| get(eff).is_none());
+        if level > 0 {
+            self.effects.insert(make(level), source.unwrap_or_default());
+        }
+    }
+
+    pub fn dying(&self) -> usize {
+        self.level(|eff| match eff { Effect::Dying { level } => Some(*level), _ => None })
+    }
+
+    fn set_dying(&mut self, level: usize) {
+        self.set_level(|eff| match eff { Effect::Dying { level } => Some(*level), _ => None }, |level| Effect::Dying { level }, level);
+    }
+
+    pub fn wounded(&self) -> usize {
+        self.level(|eff| match eff { Effect::Wounded { level } => Some(*level), _ => None })
+    }
+
+    fn set_wounded(&mut self, level: usize) {
+        self.set_level(|eff| match eff { Effect::Wounded { level } => Some(*level), _ => None }, |level| Effect::Wounded { level }, level);
+    }
+
+    pub fn drained(&self) -> usize {
+        self.level(|eff| match eff { Effect::Drained { level } => Some(*level), _ => None })
+    }
+
+    pub fn doomed(&self) -> usize {
+        self.level(|eff| match eff { Effect::Doomed { level } => Some(*level), _ => None })
+    }
+
+    // Dying at this value (or any value, once doomed reduces it to 0) is death.
+    pub fn max_dying(&self) -> usize {
+        Self::DEFAULT_DYING.saturating_sub(self.doomed())
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.dying() >= self.max_dying()
+    }
+
+    // Dropped to 0 HP: dying 1 (2 from a critical) plus wounded, and unconscious.
+    pub fn knock_out(&mut self, crit: bool, now: Round) {
+        let level = if crit { 2 } else { 1 } + self.wounded();
+        self.set_dying(std::cmp::min(level, Self::DEFAULT_DYING));
+        self.add(Effect::Unconscious { until: Lasts::Forever }, now);
+    }
+
+    // Damaged again while already dying.
+    pub fn worsen_dying(&mut self, crit: bool) {
+        let level = self.dying() + if crit { 2 } else { 1 };
+        self.set_dying(std::cmp::min(level, Self::DEFAULT_DYING));
+    }
+
+    pub fn die(&mut self) {
+        self.set_dying(Self::DEFAULT_DYING);
+    }
+
+    // Losing dying always leaves (or worsens) wounded; the creature stays unconscious at 0 HP.
+    pub fn stabilize(&mut self) {
+        if self.dying() > 0 {
+            self.set_dying(0);
+            self.set_wounded(self.wounded() + 1);
+        }
+    }
+
+    // Healed above 0 HP.
+    pub fn revive(&mut self) {
+        self.stabilize();
+        self.effects.retain(|eff, _| !matches!(eff, Effect::Unconscious { until: Lasts::Forever }));
+    }
+
+    // The flat check a dying creature makes at the start of its turn, against DC 10 + dying.
+    pub fn recovery_check<R: Roller>(&mut self, rng: &mut RandState<R>) -> Option<CheckResult> {
+        let dying = self.dying();
+        if dying == 0 || self.is_dead() {
+            return None;
+        }
+        let res = Check::flat().against("recovery", Self::RECOVERY_DC + dying as isize).roll(rng);
+        let level = match res.degree {
+            Degree::CriticalSuccess => dying.saturating_sub(2),
+            Degree::Success => dying - 1,
+            Degree::Failure => dying + 1,
+            Degree::CriticalFailure => dying + 2,
+        };
+        if level == 0 {
+            self.stabilize();
+        } else {
+            self.set_dying(std::cmp::min(level, Self::DEFAULT_DYING));
+        }
+        Some(res)
+    }
+
+    // Odds that a single persistent damage effect ends after its flat check.
+    pub fn pd_recovery_chance() -> f64 {
+        RandValue::FLAT_CHECK.chance_at_least(Self::DEFAULT_PD_DC)
+    }
+
+    pub fn before_turn<R: Roller>(&mut self, world: &mut World<R>) -> Current {
         let mut cur = self.current(world);
-        if cur.stunned > 0 {
-            if cur.stunned > cur.actions_gained {
-                cur.stunned -= cur.actions_gained;
-                cur.actions_gained = 0;
-            } else {
-                cur.actions_gained -= cur.stunned;
-            }
+        if cur.stunned_for {
+            cur.actions_gained = 0;
+        } else if cur.stunned > 0 {
+            // The actions lost pay off the stunned value, which stays on for the rest.
+            let lost = std::cmp::min(cur.stunned, cur.actions_gained);
+            cur.actions_gained -= lost;
+            cur.stunned -= lost;
+            self.set_level(|eff| match eff { Effect::Stunned { level } => Some(*level), _ => None }, |level| Effect::Stunned { level }, cur.stunned);
         }
         cur
     }
 
-    pub fn after_turn<R: Rng>(&mut self, world: &mut World<R>) -> Option<Vec<DamageResult>> {
-        // clear effects ending on _end_ of next turn:
-        use Effect::*;
+    pub fn after_turn<R: Roller>(&mut self, world: &mut World<R>) -> Option<Vec<DamageResult>> {
+        let rolls = self.after_turn_rolls(world);
+        if rolls.is_empty() {
+            None
+        } else {
+            Some(rolls.into_iter().map(|r| r.damage.result).collect())
+        }
+    }
 
-        let tm = world.time();
-        let mut damage: Option<Vec<DamageResult>> = None;
-        let mut new_effects: Option<Vec<Effect>> = None;
+    // As after_turn(), but keeps the full transcript of every persistent damage roll and flat check.
+    // Conditions wearing off are left to expire().
+    pub fn after_turn_rolls<R: Roller>(&mut self, world: &mut World<R>) -> Vec<PersistentRoll> {
+        let mut rolls: Vec<PersistentRoll> = Vec::new();
+        self.effects.retain(|eff, _| match eff {
+            Effect::PersistentDamage { dmg } => {
+                let damage = dmg.0.roll(world.rng());
+                let flat_check = RandValue::FLAT_CHECK.roll(world.rng());
+                let ended = flat_check.total >= Self::DEFAULT_PD_DC;
+                rolls.push(PersistentRoll { damage, flat_check, ended });
+                !ended
+            },
+            _ => true,
+        });
+        rolls
+    }
 
-        fn ensure_vec<T>(v: &mut Option<Vec<T>>) -> &mut Vec<T> {
-            if v.is_none() {
-                v.replace(Vec::new());
+    // Ticks every effect at the `hook` of `turn_of`'s turn, `own` if that's this creature's, and
+    // returns those that ended.
+    pub fn expire(&mut self, turn_of: CID, own: bool, hook: Hook, now: Round) -> Vec<Effect> {
+        let mut gone = Vec::new();
+        let mut kept = HashMap::new();
+        for (eff, src) in self.effects.drain() {
+            match eff.clone().tick(turn_of, own, hook, now) {
+                Some(next) => { kept.insert(next, src); },
+                None => gone.push(eff),
             }
-            v.as_mut().unwrap()
-        }
-
-        self.effects = self.effects
-            .drain()
-            .filter(|eff| {
-                match eff {
-                    &DemoralizedImmune { until, .. } if until <= tm.round => false,
-                    &Unconscious { until: Some(t), .. } if t <= tm.round => false,
-                    &Restrained { until: Some(t), .. } if t <= tm.round => false,
-                    &Immobilized { until: Some(t), .. } if t <= tm.round => false,
-                    &Paralyzed { until: Some(t), .. } if t <= tm.round => false,
-                    &Flat_Footed { until: Some(t), .. } if t <= tm.round => false,
-                    &Prone { until: Some(t), .. } if t <= tm.round => false,
-                    &Blinded { until: Some(t), ..} if t <= tm.round => false,
-                    &Frightened { level } => {
-                        if level > 1 {
-                            ensure_vec(&mut new_effects).push(Frightened { level: level - 1 });
+        }
+        self.effects = kept;
+        gone
+    }
+
+    // Marks effects sustained by `by` that match as sustained this turn; returns how many.
+    pub fn sustain<F: FnMut(&Effect) -> bool>(&mut self, by: CID, mut pred: F) -> usize {
+        let mut count = 0;
+        self.effects = mem::take(&mut self.effects).into_iter()
+            .map(|(mut eff, src)| {
+                if pred(&eff) {
+                    if let Some(Lasts::Sustained { by: b, sustained, .. }) = eff.lasts_mut() {
+                        if *b == by {
+                            *sustained = true;
+                            count += 1;
                         }
-                        false
-                    },
-                    PersistentDamage { dmg } => {
-                        ensure_vec(&mut damage).push(dmg.0.eval(world.rng()));
-                        RandValue::FLAT_CHECK.eval(world.rng()) < Self::DEFAULT_PD_DC
-                    },
-                    _ => true,
+                    }
                 }
+                (eff, src)
             })
             .collect();
-        if let Some(mut effects) = new_effects {
-            self.effects.extend(effects.drain(..));
-        }
-        damage
+        count
     }
 
-    pub fn current<R: Rng>(&self, world: &mut World<R>) -> Current {
+    pub fn current<R: Roller>(&self, world: &mut World<R>) -> Current {
         use Effect::*;
 
         let mut cur = Current::default();
-        for eff in &self.effects {
+        for eff in self.effects.keys() {
             match eff {
                 // These should only ever be present once, an invariant that add() maintains
                 Frightened { level } => cur.frightened += level,
                 Sickened { level } => cur.sickened += level,
                 Slowed { level } => cur.slowed += level,
                 Stunned { level } => cur.stunned += level,
-                Dying { level } => cur.sickened += level,
+                Stunned_For { .. } => cur.stunned_for = true,
+                Dying { level } => cur.dying += level,
                 Doomed { level } => cur.doomed += level,
                 Wounded { level } => cur.wounded += level,
                 Unconscious { .. } => {
@@ -188,17 +553,80 @@ impl Status {
                     cur.flat_footed = true;
                 },
                 Blinded { .. } => cur.blinded = true,
+                Clumsy { level } => cur.clumsy += level,
+                Drained { level } => cur.drained += level,
+                Enfeebled { level } => cur.enfeebled += level,
+                Stupefied { level } => cur.stupefied += level,
+                Fatigued { .. } => cur.fatigued = true,
+                Confused { .. } => {
+                    cur.confused = true;
+                    cur.flat_footed = true;
+                },
+                Controlled { by } => cur.controlled_by = Some(*by),
+                Dazzled { .. } => cur.dazzled = true,
+                Deafened { .. } => cur.deafened = true,
+                Fascinated { .. } => cur.fascinated = true,
+                Fleeing { .. } => cur.fleeing = true,
+                Concealed { .. } => cur.concealed = true,
+                Invisible { .. } => cur.invisible = true,
+                Hidden { from } => { cur.hidden_from.insert(*from); },
+                Undetected { from } => { cur.undetected_by.insert(*from); },
+                Quickened { only, .. } => cur.quickened = Some(only.clone()),
+                Encumbered => {
+                    cur.encumbered = true;
+                    cur.speed_penalty = Feet(10);
+                },
+                Petrified { .. } => {
+                    cur.petrified = true;
+                    cur.immobilized = true;
+                    cur.flat_footed = true;
+                },
                 _ => (),
             }
         }
+        if cur.encumbered {
+            cur.clumsy = std::cmp::max(cur.clumsy, 1);
+        }
         cur.speed_mod = 1.0;
         cur.dead = cur.dying >= Self::DEFAULT_DYING.saturating_sub(cur.doomed);
-        if cur.flat_footed { cur.ac_mod -= 2; }
+        if cur.flat_footed {
+            cur.modifiers.push(Modifier::circumstance("flat-footed", -2, Selector::Stat(Stat::Ac)));
+        }
+        if cur.prone {
+            cur.modifiers.push(Modifier::circumstance("prone", -2, Selector::Attacks));
+        }
         if cur.frightened > 0 {
-            cur.check_mod -= cur.frightened as isize;
-            cur.dc_mod -= cur.frightened as isize;
+            cur.modifiers.push(Modifier::status("frightened", -(cur.frightened as isize), Selector::All));
+        }
+        if cur.sickened > 0 {
+            cur.modifiers.push(Modifier::status("sickened", -(cur.sickened as isize), Selector::All));
+        }
+        for (name, level, ability) in [
+            ("clumsy", cur.clumsy, Ability::Dex),
+            ("drained", cur.drained, Ability::Con),
+            ("enfeebled", cur.enfeebled, Ability::Str),
+            ("stupefied", cur.stupefied, Ability::Int),
+            ("stupefied", cur.stupefied, Ability::Wis),
+            ("stupefied", cur.stupefied, Ability::Cha),
+        ] {
+            if level > 0 {
+                cur.modifiers.push(Modifier::status(name, -(level as isize), Selector::Ability(ability)));
+            }
+        }
+        if cur.fatigued {
+            cur.modifiers.push(Modifier::status("fatigued", -1, Selector::Stat(Stat::Ac)));
+            cur.modifiers.push(Modifier::status("fatigued", -1, Selector::Saves));
+        }
+        if cur.fascinated {
+            cur.modifiers.push(Modifier::status("fascinated", -2, Selector::Stat(Stat::Perception)));
+            cur.modifiers.push(Modifier::status("fascinated", -2, Selector::Skills));
+        }
+        // Strictly only for initiative and checks involving sound, which we can't tell apart.
+        if cur.deafened {
+            cur.modifiers.push(Modifier::status("deafened", -2, Selector::Stat(Stat::Perception)));
         }
-        cur.actions_gained = Self::DEFAULT_ACTIONS.saturating_sub(cur.slowed);
+        let quickened = if cur.quickened.is_some() { 1 } else { 0 };
+        cur.actions_gained = (Self::DEFAULT_ACTIONS + quickened).saturating_sub(cur.slowed);
         cur
     }
 }
@@ -0,0 +1,83 @@
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug,Clone,PartialEq,Eq,Hash,Serialize,Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Trait {
+    Aberration,
+    Animal,
+    Beast,
+    Celestial,
+    Construct,
+    Dragon,
+    Elemental,
+    Fey,
+    Fiend,
+    Fungus,
+    Giant,
+    Humanoid,
+    Monitor,
+    Ooze,
+    Plant,
+    Spirit,
+    Undead,
+    Incorporeal,
+    Mindless,
+    Swarm,
+    // Anything else (ancestries, elements, etc.) that only matters by name.
+    Other(String),
+}
+
+impl Trait {
+    const NAMED: &'static [(&'static str, Trait)] = &[
+        ("aberration", Trait::Aberration),
+        ("animal", Trait::Animal),
+        ("beast", Trait::Beast),
+        ("celestial", Trait::Celestial),
+        ("construct", Trait::Construct),
+        ("dragon", Trait::Dragon),
+        ("elemental", Trait::Elemental),
+        ("fey", Trait::Fey),
+        ("fiend", Trait::Fiend),
+        ("fungus", Trait::Fungus),
+        ("giant", Trait::Giant),
+        ("humanoid", Trait::Humanoid),
+        ("monitor", Trait::Monitor),
+        ("ooze", Trait::Ooze),
+        ("plant", Trait::Plant),
+        ("spirit", Trait::Spirit),
+        ("undead", Trait::Undead),
+        ("incorporeal", Trait::Incorporeal),
+        ("mindless", Trait::Mindless),
+        ("swarm", Trait::Swarm),
+    ];
+}
+
+impl From<String> for Trait {
+    fn from(s: String) -> Self {
+        let lower = s.to_lowercase();
+        Self::NAMED.iter()
+            .find(|(name, _)| *name == lower)
+            .map(|(_, tr)| tr.clone())
+            .unwrap_or(Trait::Other(lower))
+    }
+}
+
+impl fmt::Display for Trait {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Trait::Other(name) => write!(f, "{}", name),
+            known => {
+                let (name, _) = Self::NAMED.iter().find(|(_, tr)| tr == known).unwrap();
+                write!(f, "{}", name)
+            },
+        }
+    }
+}
+
+impl From<Trait> for String {
+    fn from(tr: Trait) -> String {
+        tr.to_string()
+    }
+}
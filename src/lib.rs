@@ -1,6 +1,10 @@
 extern crate rogue_util as util;
 extern crate bit_set;
 extern crate rand;
+extern crate rand_chacha;
+extern crate serde;
+extern crate serde_path_to_error;
+extern crate toml;
 
 pub use util::*;
 
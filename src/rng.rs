@@ -1,17 +1,59 @@
+pub mod notation;
+pub mod dist;
+pub mod transcript;
+pub mod script;
+
+pub use self::{
+    notation::*,
+    dist::*,
+    transcript::*,
+    script::*,
+};
+
 use std::ops::{Add, Sub, Mul};
 
-// Constrain T: rand::Rng
-pub struct RandState<R>(R);
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Serialize, Deserialize};
 
+// Constrain R: Roller
 #[derive(Debug,Clone)]
+pub struct RandState<R>(R);
+
+// The generator to use when fights need to be replayed: it can be seeded, snapshotted and split
+// into independent keyed streams.
+pub type SeededRandState = RandState<ChaCha12Rng>;
+
+// Everything needed to resume a SeededRandState exactly where it left off.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+pub struct Snapshot {
+    pub seed: [u8; 32],
+    pub stream: u64,
+    pub word_pos: u128,
+}
+
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
 pub enum RandValue {
     Const(isize),
     Sum(Vec<RandValue>),
     Product(Vec<RandValue>),
     Negate(Box<RandValue>),
     Die { faces: usize, times: usize},
+    // Roll `times` dice and sum only the `keep` highest (or lowest) of them.
+    Keep { faces: usize, times: usize, keep: usize, highest: bool },
+    // Each die showing `max_reroll` or less is rerolled once, and the new result stands.
+    Reroll { faces: usize, times: usize, max_reroll: usize },
+    // Each die showing its highest face is rolled again and added, up to EXPLODE_LIMIT times.
+    Explode { faces: usize, times: usize },
+    AtLeast(Box<RandValue>, isize),
+    AtMost(Box<RandValue>, isize),
+    // The whole expression rolled twice, keeping the higher (fortune) or lower (misfortune) total.
+    Fortune(Box<RandValue>),
+    Misfortune(Box<RandValue>),
 }
 
+// Multiplying dice rolls that many times as many of them, whatever the kind: "2d6 * 2" is "4d6",
+// and "4d6kh3 * 2" is "8d6kh6". Anything else is multiplied as a Product.
 impl Mul<usize> for RandValue {
     type Output = Self;
     fn mul(self, rhs: usize) -> Self {
@@ -24,6 +66,9 @@ impl Mul<usize> for RandValue {
                 Product(v)
             },
             Die { faces, times } => Die { faces, times: times*rhs },
+            Keep { faces, times, keep, highest } => Keep { faces, times: times*rhs, keep: keep*rhs, highest },
+            Reroll { faces, times, max_reroll } => Reroll { faces, times: times*rhs, max_reroll },
+            Explode { faces, times } => Explode { faces, times: times*rhs },
             other => Product(vec![other, Const(rhs as isize)]),
         }
     }
@@ -89,20 +134,137 @@ impl RandValue {
 
     pub const FLAT_CHECK: RandValue = Self::D20;
 
-    pub fn eval<R: rand::Rng>(&self, rng: &mut RandState<R>) -> isize {
+    pub const EXPLODE_LIMIT: usize = 16;
+
+    pub fn eval<R: Roller>(&self, rng: &mut RandState<R>) -> isize {
+        self.roll(rng).total
+    }
+
+    pub fn fortune(self) -> Self {
+        RandValue::Fortune(Box::new(self))
+    }
+
+    pub fn misfortune(self) -> Self {
+        RandValue::Misfortune(Box::new(self))
+    }
+
+    // How many dice the expression rolls, not counting rerolls, explosions or fortune.
+    pub fn dice_count(&self) -> usize {
         use RandValue::*;
 
         match self {
-            &Const(i) => i,
-            Sum(v) => v.iter().map(|x| x.eval(rng)).sum(),
-            Product(v) => v.iter().map(|x| x.eval(rng)).product(),
-            Negate(x) => -(x.eval(rng)),
-            Die { faces, times } => {
-                let f = *faces as isize;
-                std::iter::repeat_with(
-                    || rng.0.gen_range(1 ..= f)
-                ).take(*times).sum()
-            },
+            Const(_) => 0,
+            Sum(v) | Product(v) => v.iter().map(|x| x.dice_count()).sum(),
+            Negate(x) | AtLeast(x, _) | AtMost(x, _) | Fortune(x) | Misfortune(x) => x.dice_count(),
+            &Die { times, .. } | &Keep { times, .. } | &Reroll { times, .. } | &Explode { times, .. } => times,
+        }
+    }
+
+    // The same expression with every die replaced by one of `faces` faces.
+    pub fn with_faces(self, faces: usize) -> Self {
+        use RandValue::*;
+
+        match self {
+            Die { times, .. } => Die { faces, times },
+            Keep { times, keep, highest, .. } => Keep { faces, times, keep, highest },
+            Reroll { times, max_reroll, .. } => Reroll { faces, times, max_reroll },
+            Explode { times, .. } => Explode { faces, times },
+            Sum(v) => Sum(v.into_iter().map(|x| x.with_faces(faces)).collect()),
+            Product(v) => Product(v.into_iter().map(|x| x.with_faces(faces)).collect()),
+            Negate(x) => Negate(Box::new(x.with_faces(faces))),
+            AtLeast(x, min) => AtLeast(Box::new(x.with_faces(faces)), min),
+            AtMost(x, max) => AtMost(Box::new(x.with_faces(faces)), max),
+            Fortune(x) => Fortune(Box::new(x.with_faces(faces))),
+            Misfortune(x) => Misfortune(Box::new(x.with_faces(faces))),
+            Const(i) => Const(i),
+        }
+    }
+}
+
+impl<R> RandState<R> {
+    pub fn new(rng: R) -> Self {
+        Self(rng)
+    }
+
+    pub fn inner(&self) -> &R {
+        &self.0
+    }
+
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.0
+    }
+
+    pub fn into_inner(self) -> R {
+        self.0
+    }
+}
+
+impl<R: SeedableRng> RandState<R> {
+    pub fn from_seed(seed: R::Seed) -> Self {
+        Self(R::from_seed(seed))
+    }
+
+    pub fn seed_from_u64(seed: u64) -> Self {
+        Self(R::seed_from_u64(seed))
+    }
+
+    // A new generator seeded from this one. Deterministic, but depends on how much of this
+    // generator has been used; see substream() for forks that don't.
+    pub fn fork(&mut self) -> Self where R: RngCore {
+        Self(R::from_rng(&mut self.0).expect("seeding from an in-memory generator failed"))
+    }
+}
+
+impl SeededRandState {
+    const SUBSTREAM_DOMAIN: [u8; 9] = *b"substream";
+
+    // An independent generator derived from this one's seed and `key` alone (e.g. a CID or a trial
+    // number), so the same key yields the same rolls no matter when it is asked for.
+    pub fn substream(&self, key: u64) -> Self {
+        // Derived under a different key from this generator's own, so that no substream's seed
+        // is ever one of the words this generator rolls with, whatever stream it's on.
+        let mut seed = self.0.get_seed();
+        for (b, d) in seed.iter_mut().zip(Self::SUBSTREAM_DOMAIN.iter()) {
+            *b ^= d;
+        }
+        let mut derive = ChaCha12Rng::from_seed(seed);
+        derive.set_stream(key);
+        let mut seed = [0u8; 32];
+        derive.fill_bytes(&mut seed);
+        Self::from_seed(seed)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            seed: self.0.get_seed(),
+            stream: self.0.get_stream(),
+            word_pos: self.0.get_word_pos(),
         }
     }
+
+    pub fn restore(snapshot: &Snapshot) -> Self {
+        let mut rng = ChaCha12Rng::from_seed(snapshot.seed);
+        rng.set_stream(snapshot.stream);
+        rng.set_word_pos(snapshot.word_pos);
+        Self(rng)
+    }
+}
+
+// A source of die results. Any rand::Rng is one; so are the scripted sources in script.rs, which
+// let results come from a fixed list or from the players' own dice.
+pub trait Roller {
+    // Returns a face in 1 ..= faces.
+    fn roll_die(&mut self, faces: usize) -> usize;
+}
+
+impl<R: rand::Rng> Roller for R {
+    fn roll_die(&mut self, faces: usize) -> usize {
+        self.gen_range(1 ..= faces)
+    }
+}
+
+impl<R: Roller> RandState<R> {
+    fn die(&mut self, faces: usize) -> isize {
+        self.0.roll_die(faces) as isize
+    }
 }
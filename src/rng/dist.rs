@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+
+use super::RandValue;
+
+// An exact probability mass function over the integers. Outcomes with zero probability are never
+// stored, so the first and last keys are always the true minimum and maximum. It has no outcomes
+// at all for an expression that can't be rolled, such as one with a die of no faces.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Distribution {
+    probs: BTreeMap<isize, f64>,
+}
+
+impl Distribution {
+    pub fn constant(i: isize) -> Self {
+        let mut probs = BTreeMap::new();
+        probs.insert(i, 1.0);
+        Self { probs }
+    }
+
+    pub fn uniform(faces: usize) -> Self {
+        if faces == 0 {
+            return Self { probs: BTreeMap::new() };
+        }
+        let p = 1.0 / faces as f64;
+        Self {
+            probs: (1 ..= faces as isize).map(|i| (i, p)).collect(),
+        }
+    }
+
+    fn combine<F: Fn(isize, isize) -> isize>(&self, other: &Self, op: F) -> Self {
+        let mut probs = BTreeMap::new();
+        for (&a, &pa) in &self.probs {
+            for (&b, &pb) in &other.probs {
+                *probs.entry(op(a, b)).or_insert(0.0) += pa * pb;
+            }
+        }
+        Self { probs }
+    }
+
+    pub fn convolve(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a + b)
+    }
+
+    pub fn product(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a * b)
+    }
+
+    pub fn negate(&self) -> Self {
+        Self {
+            probs: self.probs.iter().map(|(&i, &p)| (-i, p)).collect(),
+        }
+    }
+
+    pub fn map<F: Fn(isize) -> isize>(&self, f: F) -> Self {
+        let mut probs = BTreeMap::new();
+        for (&i, &p) in &self.probs {
+            *probs.entry(f(i)).or_insert(0.0) += p;
+        }
+        Self { probs }
+    }
+
+    pub fn repeated(&self, times: usize) -> Self {
+        (0 .. times).fold(Self::constant(0), |acc, _| acc.convolve(self))
+    }
+
+    // Sum of the `keep` best (or worst) of `times` dice. Rather than enumerating every ordered
+    // roll, this walks the faces from best to worst, choosing how many dice show each face; each
+    // state is (dice left to assign, dice kept so far, kept sum) and carries the product of
+    // 1/count! so that the multinomial weight falls out at the end.
+    fn keep(faces: usize, times: usize, keep: usize, highest: bool) -> Self {
+        let keep = std::cmp::min(keep, times);
+        let mut states: BTreeMap<(usize, usize, isize), f64> = BTreeMap::new();
+        states.insert((times, 0, 0), 1.0);
+
+        let order: Vec<isize> = if highest {
+            (1 ..= faces as isize).rev().collect()
+        } else {
+            (1 ..= faces as isize).collect()
+        };
+        for face in order {
+            let mut next = BTreeMap::new();
+            for (&(left, kept, sum), &w) in &states {
+                let mut inv_fact = 1.0;
+                for count in 0 ..= left {
+                    if count > 0 { inv_fact /= count as f64; }
+                    let taken = std::cmp::min(count, keep - kept);
+                    let key = (left - count, kept + taken, sum + taken as isize * face);
+                    *next.entry(key).or_insert(0.0) += w * inv_fact;
+                }
+            }
+            states = next;
+        }
+
+        let scale = (1 ..= times).map(|i| i as f64).product::<f64>() / (faces as f64).powi(times as i32);
+        let mut probs = BTreeMap::new();
+        for ((left, _, sum), w) in states {
+            if left == 0 {
+                *probs.entry(sum).or_insert(0.0) += w * scale;
+            }
+        }
+        Self { probs }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(isize, f64)> + '_ {
+        self.probs.iter().map(|(&i, &p)| (i, p))
+    }
+
+    pub fn prob(&self, i: isize) -> f64 {
+        self.probs.get(&i).copied().unwrap_or(0.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.probs.is_empty()
+    }
+
+    pub fn min(&self) -> Option<isize> {
+        self.probs.keys().next().copied()
+    }
+
+    pub fn max(&self) -> Option<isize> {
+        self.probs.keys().next_back().copied()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.iter().map(|(i, p)| i as f64 * p).sum()
+    }
+
+    pub fn variance(&self) -> f64 {
+        let mean = self.mean();
+        self.iter().map(|(i, p)| (i as f64 - mean).powi(2) * p).sum()
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    pub fn at_least(&self, n: isize) -> f64 {
+        self.probs.range(n ..).map(|(_, &p)| p).sum()
+    }
+
+    pub fn at_most(&self, n: isize) -> f64 {
+        self.probs.range(..= n).map(|(_, &p)| p).sum()
+    }
+}
+
+impl RandValue {
+    pub fn distribution(&self) -> Distribution {
+        use RandValue::*;
+
+        match self {
+            &Const(i) => Distribution::constant(i),
+            Sum(v) => v.iter().fold(
+                Distribution::constant(0),
+                |acc, x| acc.convolve(&x.distribution()),
+            ),
+            Product(v) => v.iter().fold(
+                Distribution::constant(1),
+                |acc, x| acc.product(&x.distribution()),
+            ),
+            Negate(x) => x.distribution().negate(),
+            &Die { faces, times } => Distribution::uniform(faces).repeated(times),
+            &Keep { faces, times, keep, highest } => Distribution::keep(faces, times, keep, highest),
+            &Reroll { faces, times, max_reroll } => {
+                let f = faces as f64;
+                let rerolled = std::cmp::min(max_reroll, faces) as f64;
+                let one = Distribution {
+                    probs: (1 ..= faces as isize).map(|i| {
+                        let kept = if i as usize > max_reroll { 1.0 / f } else { 0.0 };
+                        (i, kept + rerolled / f / f)
+                    }).filter(|&(_, p)| p > 0.0).collect(),
+                };
+                one.repeated(times)
+            },
+            &Explode { faces, times } => {
+                // The last permitted roll never explodes, which is exactly what eval() does.
+                let uniform = Distribution::uniform(faces);
+                let top = faces as isize;
+                let p = 1.0 / faces as f64;
+                let one = (0 .. Self::EXPLODE_LIMIT).fold(uniform.clone(), |deeper, _| {
+                    let mut probs: BTreeMap<isize, f64> = (1 .. top).map(|i| (i, p)).collect();
+                    for (i, q) in deeper.iter() {
+                        *probs.entry(top + i).or_insert(0.0) += p * q;
+                    }
+                    Distribution { probs }
+                });
+                one.repeated(times)
+            },
+            AtLeast(x, min) => x.distribution().map(|i| std::cmp::max(i, *min)),
+            AtMost(x, max) => x.distribution().map(|i| std::cmp::min(i, *max)),
+            Fortune(x) => {
+                let one = x.distribution();
+                one.combine(&one, std::cmp::max)
+            },
+            Misfortune(x) => {
+                let one = x.distribution();
+                one.combine(&one, std::cmp::min)
+            },
+        }
+    }
+
+    pub fn min(&self) -> Option<isize> { self.distribution().min() }
+    pub fn max(&self) -> Option<isize> { self.distribution().max() }
+    pub fn mean(&self) -> f64 { self.distribution().mean() }
+    pub fn variance(&self) -> f64 { self.distribution().variance() }
+
+    pub fn chance_at_least(&self, n: isize) -> f64 {
+        self.distribution().at_least(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dist(s: &str) -> Distribution {
+        s.parse::<RandValue>().unwrap().distribution()
+    }
+
+    // Every ordered roll of `times` dice, scored by `score`.
+    fn enumerated<F: Fn(&[isize]) -> isize>(faces: usize, times: usize, score: F) -> Distribution {
+        let total = faces.pow(times as u32);
+        let mut probs = BTreeMap::new();
+        for n in 0 .. total {
+            let roll: Vec<isize> = (0 .. times)
+                .map(|idx| (n / faces.pow(idx as u32) % faces) as isize + 1)
+                .collect();
+            *probs.entry(score(&roll)).or_insert(0.0) += 1.0 / total as f64;
+        }
+        Distribution { probs }
+    }
+
+    fn assert_same(got: &Distribution, want: &Distribution) {
+        assert_eq!(got.probs.keys().collect::<Vec<_>>(), want.probs.keys().collect::<Vec<_>>());
+        for (i, p) in want.iter() {
+            assert!((got.prob(i) - p).abs() < 1e-12, "P({}) is {}, not {}", i, got.prob(i), p);
+        }
+    }
+
+    #[test]
+    fn sums() {
+        let d = dist("2d6+1");
+        assert_same(&d, &enumerated(6, 2, |r| r[0] + r[1] + 1));
+        assert_eq!((d.min(), d.max()), (Some(3), Some(13)));
+        assert!((d.prob(8) - 6.0 / 36.0).abs() < 1e-12);
+        assert_same(&dist("1d4*2-1d4"), &enumerated(4, 2, |r| r[0] * 2 - r[1]));
+    }
+
+    #[test]
+    fn keep() {
+        let best3 = |r: &[isize]| {
+            let mut r = r.to_vec();
+            r.sort();
+            r[1 ..].iter().sum()
+        };
+        assert_same(&dist("4d6kh3"), &enumerated(6, 4, best3));
+        assert_same(&dist("2d20kl"), &enumerated(20, 2, |r| std::cmp::min(r[0], r[1])));
+        assert_same(&dist("3d4kh5"), &enumerated(4, 3, |r| r.iter().sum()));
+    }
+
+    #[test]
+    fn reroll() {
+        // The second die only matters when the first shows 1 or 2.
+        let d = dist("1d6r2");
+        assert_same(&d, &enumerated(6, 2, |r| if r[0] <= 2 { r[1] } else { r[0] }));
+        assert!((d.prob(1) - 2.0 / 36.0).abs() < 1e-12);
+        assert_same(&dist("2d4r1"), &enumerated(4, 4, |r| {
+            (if r[0] <= 1 { r[1] } else { r[0] }) + (if r[2] <= 1 { r[3] } else { r[2] })
+        }));
+    }
+
+    #[test]
+    fn explode() {
+        let d = dist("1d4!");
+        for i in 1 ..= 3 {
+            assert!((d.prob(i) - 0.25).abs() < 1e-12);
+        }
+        assert_eq!(d.prob(4), 0.0);
+        assert!((d.prob(6) - 1.0 / 16.0).abs() < 1e-12);
+        assert!((d.prob(9) - 1.0 / 64.0).abs() < 1e-12);
+        // The last permitted die doesn't explode, so the chain stops at 4 * EXPLODE_LIMIT + 4.
+        let top = 4 * RandValue::EXPLODE_LIMIT as isize + 4;
+        assert_eq!(d.max(), Some(top));
+        assert!((d.prob(top) - 0.25f64.powi(RandValue::EXPLODE_LIMIT as i32 + 1)).abs() < 1e-15);
+        assert!((d.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((d.mean() - 10.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clamp() {
+        assert_same(&dist("max(1d4-2, 1)"), &enumerated(4, 1, |r| std::cmp::max(r[0] - 2, 1)));
+        assert_same(&dist("min(2d6, 4)"), &enumerated(6, 2, |r| std::cmp::min(r[0] + r[1], 4)));
+    }
+
+    #[test]
+    fn fortune() {
+        let d = dist("fortune(d20)");
+        assert!((d.prob(20) - 39.0 / 400.0).abs() < 1e-12);
+        assert!((d.mean() - 13.825).abs() < 1e-12);
+        assert!((dist("misfortune(d20)").prob(1) - 39.0 / 400.0).abs() < 1e-12);
+        // The whole expression is rolled twice, not each die.
+        assert_same(&dist("fortune(2d4+1)"), &enumerated(4, 4, |r| std::cmp::max(r[0] + r[1], r[2] + r[3]) + 1));
+        assert_same(&dist("misfortune(2d4+1)"), &enumerated(4, 4, |r| std::cmp::min(r[0] + r[1], r[2] + r[3]) + 1));
+    }
+
+    #[test]
+    fn empty() {
+        let none = RandValue::Die { faces: 0, times: 1 };
+        assert!(none.distribution().is_empty());
+        assert_eq!((none.min(), none.max()), (None, None));
+        assert!(Distribution::uniform(0).is_empty());
+        // Nothing that depends on an unrollable part can be rolled either.
+        assert!((RandValue::D6 + none.clone()).distribution().is_empty());
+        assert!(RandValue::Product(vec![RandValue::D6, none.clone()]).distribution().is_empty());
+        assert!(RandValue::Fortune(Box::new(none)).distribution().is_empty());
+    }
+}
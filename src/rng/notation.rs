@@ -0,0 +1,393 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+use super::RandValue;
+
+// Grammar (whitespace is ignored between tokens):
+//
+//   expr   := term (('+' term) | term_starting_with_'-')*
+//   term   := unary ('*' unary)*
+//   unary  := '-' unary | atom
+//   atom   := NUM | dice | '(' expr ')' | ('min' | 'max') '(' expr ',' '-'? NUM ')'
+//           | ('fortune' | 'misfortune') '(' expr ')'
+//   dice   := NUM? 'd' NUM ('kh' NUM? | 'kl' NUM? | 'r' NUM | '!')?
+//
+// "max(x, n)" is x floored at n (AtLeast), and "min(x, n)" is x capped at n (AtMost).
+// "fortune(x)" rolls x twice and takes the higher total; "misfortune(x)" the lower.
+//
+// A binary minus is read as a sum with a negated term, so "2d6-1" is Sum([2d6, -1]). A minus
+// directly before a bare number folds into a negative Const; before anything else it produces a
+// Negate. Display mirrors these rules exactly so that printing and reparsing is the identity, except
+// that an empty Sum or Product, which can't be written, prints as the constant it comes to.
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum ParseError {
+    UnexpectedEnd,
+    UnexpectedChar { pos: usize, found: char },
+    BadNumber { pos: usize },
+    ZeroFaces { pos: usize },
+    TrailingInput { pos: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ParseError::*;
+
+        match self {
+            UnexpectedEnd => write!(f, "unexpected end of dice expression"),
+            UnexpectedChar { pos, found } => write!(f, "unexpected {:?} at offset {}", found, pos),
+            BadNumber { pos } => write!(f, "number out of range at offset {}", pos),
+            ZeroFaces { pos } => write!(f, "die with zero faces at offset {}", pos),
+            TrailingInput { pos } => write!(f, "trailing input at offset {}", pos),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_ws(&mut self) {
+        while let Some(c) = self.src[self.pos..].chars().next() {
+            if !c.is_whitespace() { break; }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_ws();
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn expect(&mut self, want: char) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(c) if c == want => { self.bump(); Ok(()) },
+            Some(c) => Err(ParseError::UnexpectedChar { pos: self.pos, found: c }),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, ParseError> {
+        self.skip_ws();
+        let start = self.pos;
+        let len = self.src[start..].bytes().take_while(|b| b.is_ascii_digit()).count();
+        if len == 0 {
+            return Err(match self.peek() {
+                Some(c) => ParseError::UnexpectedChar { pos: start, found: c },
+                None => ParseError::UnexpectedEnd,
+            });
+        }
+        self.pos += len;
+        self.src[start .. self.pos].parse::<usize>()
+            .ok()
+            .filter(|&n| n <= isize::MAX as usize)
+            .ok_or(ParseError::BadNumber { pos: start })
+    }
+
+    fn signed(&mut self) -> Result<isize, ParseError> {
+        let neg = if let Some('-') = self.peek() { self.bump(); true } else { false };
+        let n = self.number()? as isize;
+        Ok(if neg { -n } else { n })
+    }
+
+    fn word(&mut self) -> &'a str {
+        self.skip_ws();
+        let start = self.pos;
+        let len = self.src[start..].bytes().take_while(|b| b.is_ascii_alphabetic()).count();
+        self.pos += len;
+        &self.src[start .. self.pos]
+    }
+
+    fn dice(&mut self, times: usize) -> Result<RandValue, ParseError> {
+        let pos = self.pos;
+        let faces = self.number()?;
+        if faces == 0 {
+            return Err(ParseError::ZeroFaces { pos });
+        }
+        let rest = &self.src[self.pos..];
+        Ok(if rest.starts_with("kh") || rest.starts_with("kl") {
+            let highest = rest.starts_with("kh");
+            self.pos += 2;
+            let keep = match self.src[self.pos..].chars().next() {
+                Some(c) if c.is_ascii_digit() => self.number()?,
+                _ => 1,
+            };
+            RandValue::Keep { faces, times, keep, highest }
+        } else if rest.starts_with('r') {
+            self.pos += 1;
+            RandValue::Reroll { faces, times, max_reroll: self.number()? }
+        } else if rest.starts_with('!') {
+            self.pos += 1;
+            RandValue::Explode { faces, times }
+        } else {
+            RandValue::Die { faces, times }
+        })
+    }
+
+    fn expr(&mut self) -> Result<RandValue, ParseError> {
+        let mut terms = vec![self.term()?];
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.bump();
+                    terms.push(self.term()?);
+                },
+                Some('-') => terms.push(self.term()?),
+                _ => break,
+            }
+        }
+        Ok(if terms.len() == 1 { terms.pop().unwrap() } else { RandValue::Sum(terms) })
+    }
+
+    fn term(&mut self) -> Result<RandValue, ParseError> {
+        let mut factors = vec![self.unary()?.0];
+        while let Some('*') = self.peek() {
+            self.bump();
+            factors.push(self.unary()?.0);
+        }
+        Ok(if factors.len() == 1 { factors.pop().unwrap() } else { RandValue::Product(factors) })
+    }
+
+    // The flag is true if the value was a bare numeric literal, which a leading minus folds into.
+    fn unary(&mut self) -> Result<(RandValue, bool), ParseError> {
+        if let Some('-') = self.peek() {
+            self.bump();
+            let (inner, literal) = self.unary()?;
+            return Ok(match inner {
+                RandValue::Const(i) if literal => (RandValue::Const(-i), false),
+                other => (RandValue::Negate(Box::new(other)), false),
+            });
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<(RandValue, bool), ParseError> {
+        match self.peek() {
+            Some('(') => {
+                self.bump();
+                let inner = self.expr()?;
+                self.expect(')')?;
+                Ok((inner, false))
+            },
+            Some(c) if c.is_ascii_digit() => {
+                let times = self.number()?;
+                match self.src[self.pos..].chars().next() {
+                    Some('d') | Some('D') => {
+                        self.pos += 1;
+                        Ok((self.dice(times)?, false))
+                    },
+                    _ => Ok((RandValue::Const(times as isize), true)),
+                }
+            },
+            Some('d') | Some('D') => {
+                self.bump();
+                Ok((self.dice(1)?, false))
+            },
+            Some(c) if c.is_ascii_alphabetic() => {
+                let pos = self.pos;
+                let clamp_min = match self.word() {
+                    "max" => true,
+                    "min" => false,
+                    word @ ("fortune" | "misfortune") => {
+                        self.expect('(')?;
+                        let inner = self.expr()?;
+                        self.expect(')')?;
+                        return Ok((if word == "fortune" { inner.fortune() } else { inner.misfortune() }, false));
+                    },
+                    _ => return Err(ParseError::UnexpectedChar { pos, found: c }),
+                };
+                self.expect('(')?;
+                let inner = Box::new(self.expr()?);
+                self.expect(',')?;
+                let bound = self.signed()?;
+                self.expect(')')?;
+                Ok((if clamp_min {
+                    RandValue::AtLeast(inner, bound)
+                } else {
+                    RandValue::AtMost(inner, bound)
+                }, false))
+            },
+            Some(c) => Err(ParseError::UnexpectedChar { pos: self.pos, found: c }),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+}
+
+impl FromStr for RandValue {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser { src: s, pos: 0 };
+        let value = parser.expr()?;
+        match parser.peek() {
+            None => Ok(value),
+            Some(_) => Err(ParseError::TrailingInput { pos: parser.pos }),
+        }
+    }
+}
+
+impl RandValue {
+    fn fmt_sum_child(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RandValue::Sum(_) => write!(f, "({})", self),
+            other => other.fmt_term(f),
+        }
+    }
+
+    fn fmt_term(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RandValue::Product(v) if v.is_empty() => write!(f, "1"),
+            RandValue::Product(v) => {
+                for (idx, x) in v.iter().enumerate() {
+                    if idx > 0 { write!(f, "*")?; }
+                    match x {
+                        RandValue::Sum(_) | RandValue::Product(_) => write!(f, "({})", x)?,
+                        other => other.fmt_factor(f)?,
+                    }
+                }
+                Ok(())
+            },
+            other => other.fmt_factor(f),
+        }
+    }
+
+    fn fmt_factor(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use RandValue::*;
+
+        match self {
+            Const(i) => write!(f, "{}", i),
+            Die { faces, times } => write!(f, "{}d{}", times, faces),
+            Keep { faces, times, keep, highest } => {
+                write!(f, "{}d{}{}{}", times, faces, if *highest { "kh" } else { "kl" }, keep)
+            },
+            Reroll { faces, times, max_reroll } => write!(f, "{}d{}r{}", times, faces, max_reroll),
+            Explode { faces, times } => write!(f, "{}d{}!", times, faces),
+            AtLeast(x, min) => write!(f, "max({},{})", x, min),
+            AtMost(x, max) => write!(f, "min({},{})", x, max),
+            Fortune(x) => write!(f, "fortune({})", x),
+            Misfortune(x) => write!(f, "misfortune({})", x),
+            Negate(x) => match &**x {
+                Const(_) | Sum(_) | Product(_) | Negate(_) => write!(f, "-({})", x),
+                _ => write!(f, "-{}", x),
+            },
+            Sum(_) | Product(_) => write!(f, "({})", self),
+        }
+    }
+}
+
+impl fmt::Display for RandValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RandValue::Sum(v) if v.is_empty() => write!(f, "0"),
+            RandValue::Sum(v) => {
+                for (idx, x) in v.iter().enumerate() {
+                    // A child printing with a leading minus is reparsed as a negated term, so the
+                    // '+' is only needed before children that don't.
+                    if idx > 0 && !x.to_string().starts_with('-') { write!(f, "+")?; }
+                    x.fmt_sum_child(f)?;
+                }
+                Ok(())
+            },
+            other => other.fmt_term(f),
+        }
+    }
+}
+
+// Dice expressions are stored in files the way GMs write them.
+impl Serialize for RandValue {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RandValue {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RandValue::*;
+
+    // Parses, prints and parses again, which must give the same value.
+    fn round_trip(s: &str) -> RandValue {
+        let value: RandValue = s.parse().unwrap();
+        let printed = value.to_string();
+        assert_eq!(printed.parse::<RandValue>(), Ok(value.clone()), "{:?} printed as {:?}", s, printed);
+        value
+    }
+
+    fn die(times: usize, faces: usize) -> RandValue {
+        Die { faces, times }
+    }
+
+    #[test]
+    fn numbers_and_dice() {
+        assert_eq!(round_trip("4"), Const(4));
+        assert_eq!(round_trip("d20"), die(1, 20));
+        assert_eq!(round_trip("2d6"), die(2, 6));
+        assert_eq!(round_trip(" 2d6 + 1 "), Sum(vec![die(2, 6), Const(1)]));
+        assert_eq!(round_trip("4d6kh3"), Keep { faces: 6, times: 4, keep: 3, highest: true });
+        assert_eq!(round_trip("2d20kl"), Keep { faces: 20, times: 2, keep: 1, highest: false });
+        assert_eq!(round_trip("2d6r1"), Reroll { faces: 6, times: 2, max_reroll: 1 });
+        assert_eq!(round_trip("3d6!"), Explode { faces: 6, times: 3 });
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(round_trip("-4"), Const(-4));
+        assert_eq!(round_trip("-1d4"), Negate(Box::new(die(1, 4))));
+        assert_eq!(round_trip("-(4)"), Negate(Box::new(Const(4))));
+        assert_eq!(round_trip("--4"), Negate(Box::new(Const(-4))));
+        assert_eq!(round_trip("2d6-1"), Sum(vec![die(2, 6), Const(-1)]));
+        assert_eq!(round_trip("1d8-1d4"), Sum(vec![die(1, 8), Negate(Box::new(die(1, 4)))]));
+        assert_eq!(round_trip("3*(1d8+2)"), Product(vec![Const(3), Sum(vec![die(1, 8), Const(2)])]));
+        assert_eq!(round_trip("1d4*2*-3"), Product(vec![die(1, 4), Const(2), Const(-3)]));
+        assert_eq!(round_trip("-(1d4+1)"), Negate(Box::new(Sum(vec![die(1, 4), Const(1)]))));
+        round_trip("(1d4+1)+(2d6-3)*2");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(round_trip("max(1d4-2, 1)"), AtLeast(Box::new(Sum(vec![die(1, 4), Const(-2)])), 1));
+        assert_eq!(round_trip("min(2d6,-1)"), AtMost(Box::new(die(2, 6)), -1));
+        assert_eq!(round_trip("fortune(1d20+5)"), Fortune(Box::new(Sum(vec![die(1, 20), Const(5)]))));
+        assert_eq!(round_trip("misfortune(d20)"), Misfortune(Box::new(die(1, 20))));
+    }
+
+    #[test]
+    fn printing() {
+        for s in ["-1d4", "3*(1d8+2)", "2d6-1", "4d6kh3", "max(1d4,1)", "fortune(1d20)"] {
+            assert_eq!(s.parse::<RandValue>().unwrap().to_string(), s);
+        }
+        assert_eq!(Sum(Vec::new()).to_string(), "0");
+        assert_eq!(Product(Vec::new()).to_string(), "1");
+        let nested = Sum(vec![die(1, 6), Product(Vec::new()), Negate(Box::new(Sum(Vec::new())))]);
+        assert_eq!(nested.to_string(), "1d6+1-(0)");
+        assert_eq!(round_trip(&nested.to_string()), Sum(vec![die(1, 6), Const(1), Negate(Box::new(Const(0)))]));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!("".parse::<RandValue>(), Err(ParseError::UnexpectedEnd));
+        assert_eq!("2d0".parse::<RandValue>(), Err(ParseError::ZeroFaces { pos: 2 }));
+        assert_eq!("2d6)".parse::<RandValue>(), Err(ParseError::TrailingInput { pos: 3 }));
+        assert_eq!("2d6+x".parse::<RandValue>(), Err(ParseError::UnexpectedChar { pos: 4, found: 'x' }));
+        assert_eq!("max(2d6)".parse::<RandValue>(), Err(ParseError::UnexpectedChar { pos: 7, found: ')' }));
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::VecDeque;
+
+use super::{RandState, Roller};
+
+// Die results taken in order from a fixed list, for tests and replaying physical rolls. Running
+// out of results, or a result that the die being rolled can't show, is a bug in the script and
+// panics.
+#[derive(Debug,Clone,Default,PartialEq,Eq)]
+pub struct Script {
+    rolls: VecDeque<usize>,
+}
+
+impl Script {
+    pub fn new<I: IntoIterator<Item=usize>>(rolls: I) -> Self {
+        Self { rolls: rolls.into_iter().collect() }
+    }
+
+    pub fn push(&mut self, face: usize) {
+        self.rolls.push_back(face);
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.rolls.len()
+    }
+}
+
+impl Roller for Script {
+    fn roll_die(&mut self, faces: usize) -> usize {
+        let face = self.rolls.pop_front()
+            .unwrap_or_else(|| panic!("script ran out of rolls (wanted a d{})", faces));
+        if face < 1 || face > faces {
+            panic!("scripted roll {} is impossible on a d{}", face, faces);
+        }
+        face
+    }
+}
+
+// Die results supplied on demand, e.g. by prompting the GM for what the player rolled. The
+// callback is given the number of faces and must return a face in 1 ..= faces.
+pub struct Prompt<F>(pub F);
+
+impl<F: FnMut(usize) -> usize> Roller for Prompt<F> {
+    fn roll_die(&mut self, faces: usize) -> usize {
+        let face = (self.0)(faces);
+        if face < 1 || face > faces {
+            panic!("prompted roll {} is impossible on a d{}", face, faces);
+        }
+        face
+    }
+}
+
+impl RandState<Script> {
+    pub fn scripted<I: IntoIterator<Item=usize>>(rolls: I) -> Self {
+        Self::new(Script::new(rolls))
+    }
+}
+
+impl<F: FnMut(usize) -> usize> RandState<Prompt<F>> {
+    pub fn prompted(f: F) -> Self {
+        Self::new(Prompt(f))
+    }
+}
@@ -0,0 +1,205 @@
+use std::fmt;
+
+use super::{RandValue, RandState, Roller};
+
+// The record of one evaluation of a RandValue: its total and how that total came about, mirroring
+// the shape of the expression that was rolled.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Roll {
+    pub total: isize,
+    pub detail: Detail,
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum Detail {
+    Const,
+    Sum(Vec<Roll>),
+    Product(Vec<Roll>),
+    Negate(Box<Roll>),
+    Dice { faces: usize, dice: Vec<DieRoll> },
+    AtLeast { inner: Box<Roll>, min: isize },
+    AtMost { inner: Box<Roll>, max: isize },
+    // Both rolls of a fortune or misfortune, and which of them counted.
+    Twice { rolls: Vec<Roll>, kept: usize, highest: bool },
+}
+
+// Every face one die showed, in order. For a rerolled die only the last face counts; for an
+// exploding die they are all added together. `value` is what the die contributed if kept.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct DieRoll {
+    pub faces: Vec<isize>,
+    pub value: isize,
+    pub kept: bool,
+}
+
+impl DieRoll {
+    fn single(face: isize) -> Self {
+        Self { faces: vec![face], value: face, kept: true }
+    }
+}
+
+impl RandValue {
+    pub fn roll<R: Roller>(&self, rng: &mut RandState<R>) -> Roll {
+        use RandValue::*;
+
+        let (total, detail) = match self {
+            &Const(i) => (i, Detail::Const),
+            Sum(v) => {
+                let parts: Vec<Roll> = v.iter().map(|x| x.roll(rng)).collect();
+                (parts.iter().map(|r| r.total).sum(), Detail::Sum(parts))
+            },
+            Product(v) => {
+                let parts: Vec<Roll> = v.iter().map(|x| x.roll(rng)).collect();
+                (parts.iter().map(|r| r.total).product(), Detail::Product(parts))
+            },
+            Negate(x) => {
+                let inner = x.roll(rng);
+                (-inner.total, Detail::Negate(Box::new(inner)))
+            },
+            &Die { faces, times } => {
+                let dice: Vec<DieRoll> = (0 .. times).map(|_| DieRoll::single(rng.die(faces))).collect();
+                (dice.iter().map(|d| d.value).sum(), Detail::Dice { faces, dice })
+            },
+            &Keep { faces, times, keep, highest } => {
+                let mut dice: Vec<DieRoll> = (0 .. times).map(|_| DieRoll::single(rng.die(faces))).collect();
+                let mut order: Vec<usize> = (0 .. times).collect();
+                order.sort_by_key(|&i| dice[i].value);
+                if highest { order.reverse(); }
+                for &i in order.iter().skip(keep) {
+                    dice[i].kept = false;
+                }
+                (dice.iter().filter(|d| d.kept).map(|d| d.value).sum(), Detail::Dice { faces, dice })
+            },
+            &Reroll { faces, times, max_reroll } => {
+                let dice: Vec<DieRoll> = (0 .. times).map(|_| {
+                    let first = rng.die(faces);
+                    if first <= max_reroll as isize {
+                        let second = rng.die(faces);
+                        DieRoll { faces: vec![first, second], value: second, kept: true }
+                    } else {
+                        DieRoll::single(first)
+                    }
+                }).collect();
+                (dice.iter().map(|d| d.value).sum(), Detail::Dice { faces, dice })
+            },
+            &Explode { faces, times } => {
+                let dice: Vec<DieRoll> = (0 .. times).map(|_| {
+                    let mut rolled = Vec::new();
+                    for _ in 0 ..= Self::EXPLODE_LIMIT {
+                        let face = rng.die(faces);
+                        rolled.push(face);
+                        if face < faces as isize { break; }
+                    }
+                    DieRoll { value: rolled.iter().sum(), faces: rolled, kept: true }
+                }).collect();
+                (dice.iter().map(|d| d.value).sum(), Detail::Dice { faces, dice })
+            },
+            AtLeast(x, min) => {
+                let inner = x.roll(rng);
+                (std::cmp::max(inner.total, *min), Detail::AtLeast { inner: Box::new(inner), min: *min })
+            },
+            AtMost(x, max) => {
+                let inner = x.roll(rng);
+                (std::cmp::min(inner.total, *max), Detail::AtMost { inner: Box::new(inner), max: *max })
+            },
+            Fortune(x) | Misfortune(x) => {
+                let highest = matches!(self, Fortune(_));
+                let rolls = vec![x.roll(rng), x.roll(rng)];
+                // The first roll stands on a tie.
+                let kept = match (highest, rolls[1].total.cmp(&rolls[0].total)) {
+                    (true, std::cmp::Ordering::Greater) | (false, std::cmp::Ordering::Less) => 1,
+                    _ => 0,
+                };
+                (rolls[kept].total, Detail::Twice { rolls, kept, highest })
+            },
+        };
+        Roll { total, detail }
+    }
+}
+
+impl<R: Roller> RandState<R> {
+    pub fn roll(&mut self, value: &RandValue) -> Roll {
+        value.roll(self)
+    }
+}
+
+impl Roll {
+    // All dice rolled anywhere in this transcript, in evaluation order.
+    pub fn dice(&self) -> Vec<&DieRoll> {
+        let mut out = Vec::new();
+        self.collect_dice(&mut out);
+        out
+    }
+
+    fn collect_dice<'a>(&'a self, out: &mut Vec<&'a DieRoll>) {
+        match &self.detail {
+            Detail::Const => (),
+            Detail::Sum(v) | Detail::Product(v) | Detail::Twice { rolls: v, .. } => {
+                for r in v { r.collect_dice(out); }
+            },
+            Detail::Negate(r) | Detail::AtLeast { inner: r, .. } | Detail::AtMost { inner: r, .. } => {
+                r.collect_dice(out);
+            },
+            Detail::Dice { dice, .. } => out.extend(dice.iter()),
+        }
+    }
+
+    fn fmt_detail(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.detail {
+            Detail::Const => write!(f, "{}", self.total),
+            Detail::Sum(v) | Detail::Product(v) => {
+                let sep = if let Detail::Sum(_) = self.detail { " + " } else { " * " };
+                write!(f, "(")?;
+                for (idx, r) in v.iter().enumerate() {
+                    if idx > 0 { write!(f, "{}", sep)?; }
+                    r.fmt_detail(f)?;
+                }
+                write!(f, ")")
+            },
+            Detail::Negate(r) => {
+                write!(f, "-")?;
+                r.fmt_detail(f)
+            },
+            Detail::Dice { faces, dice } => {
+                write!(f, "d{}[", faces)?;
+                for (idx, d) in dice.iter().enumerate() {
+                    if idx > 0 { write!(f, ", ")?; }
+                    if !d.kept { write!(f, "~")?; }
+                    for (fidx, face) in d.faces.iter().enumerate() {
+                        if fidx > 0 { write!(f, "/")?; }
+                        write!(f, "{}", face)?;
+                    }
+                }
+                write!(f, "]")
+            },
+            Detail::AtLeast { inner, min } => {
+                write!(f, "max(")?;
+                inner.fmt_detail(f)?;
+                write!(f, ", {})", min)
+            },
+            Detail::AtMost { inner, max } => {
+                write!(f, "min(")?;
+                inner.fmt_detail(f)?;
+                write!(f, ", {})", max)
+            },
+            Detail::Twice { rolls, kept, highest } => {
+                write!(f, "{}(", if *highest { "fortune" } else { "misfortune" })?;
+                for (idx, r) in rolls.iter().enumerate() {
+                    if idx > 0 { write!(f, ", ")?; }
+                    if idx != *kept { write!(f, "~")?; }
+                    r.fmt_detail(f)?;
+                }
+                write!(f, ")")
+            },
+        }
+    }
+}
+
+// Renders e.g. "(d6[3, 5] + 4) = 12". Dropped dice are marked with '~', and the successive faces
+// of a rerolled or exploding die are separated by '/'.
+impl fmt::Display for Roll {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_detail(f)?;
+        write!(f, " = {}", self.total)
+    }
+}
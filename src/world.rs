@@ -1,8 +1,19 @@
 pub mod space;
 pub mod time;
 pub mod terrain;
+pub mod bestiary;
+pub mod initiative;
+pub mod action;
+pub mod strike;
+pub mod save;
+pub mod damage;
+pub mod effect;
+pub mod affliction;
 
 use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
 
 use util::grid::{self, region};
 
@@ -10,12 +21,91 @@ use crate::creature::{self, *};
 use crate::rng;
 
 pub struct World<R> {
-    bestiary: HashMap<String, creature::Creature>,
+    bestiary: HashMap<String, Rc<creature::Creature>>,
     creatures: HashMap<creature::CID, creature::State>,
     initiative: VecDeque<creature::CID>,
     rng: rng::RandState<R>,
     time: time::Time,
     map: region::Region<terrain::Square>,
+    next_cid: usize,
+    // Delaying creatures, with what they had left of the turn they Delayed.
+    delaying: HashMap<creature::CID, creature::Budget>,
+    // What the active creature's turn began with, until it hands the turn on; Delay is only
+    // allowed while its budget still matches.
+    turn_budget: Option<creature::Budget>,
+    readied: HashMap<creature::CID, initiative::Readied>,
+    // Set while a creature that returned from delaying takes its turn; its end-of-turn effects
+    // already happened when it delayed.
+    returned: Option<creature::CID>,
+}
+
+pub struct WorldBuilder<R> {
+    bestiary: HashMap<String, Rc<creature::Creature>>,
+    rng: rng::RandState<R>,
+    time: time::Time,
+    map: region::Region<terrain::Square>,
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum SpawnError {
+    UnknownCreature(String),
+    // Unpassable terrain, or off the edge of the map.
+    Blocked(space::Location),
+    Occupied { loc: space::Location, by: creature::CID },
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpawnError::UnknownCreature(name) => write!(f, "no creature named {:?} in the bestiary", name),
+            SpawnError::Blocked(loc) => write!(f, "{:?} can't be occupied", loc),
+            SpawnError::Occupied { loc, by } => write!(f, "{:?} is already occupied by {:?}", loc, by),
+        }
+    }
+}
+
+impl Error for SpawnError {}
+
+impl<R> WorldBuilder<R> {
+    pub fn new(map: region::Region<terrain::Square>, rng: rng::RandState<R>) -> Self {
+        Self {
+            bestiary: HashMap::new(),
+            rng,
+            time: time::Time { round: time::Round(0), turn: time::Turn(0) },
+            map,
+        }
+    }
+
+    pub fn creature(mut self, key: &str, creature: creature::Creature) -> Self {
+        self.bestiary.insert(key.to_string(), Rc::new(creature));
+        self
+    }
+
+    pub fn bestiary(mut self, text: &str) -> Result<Self, bestiary::LoadError> {
+        self.bestiary.extend(bestiary::load(text)?.into_iter().map(|(k, c)| (k, Rc::new(c))));
+        Ok(self)
+    }
+
+    pub fn time(mut self, time: time::Time) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub fn build(self) -> World<R> {
+        World {
+            bestiary: self.bestiary,
+            creatures: HashMap::new(),
+            initiative: VecDeque::new(),
+            rng: self.rng,
+            time: self.time,
+            map: self.map,
+            next_cid: 0,
+            delaying: HashMap::new(),
+            turn_budget: None,
+            readied: HashMap::new(),
+            returned: None,
+        }
+    }
 }
 
 impl<R> World<R> {
@@ -26,4 +116,121 @@ impl<R> World<R> {
     pub fn rng(&mut self) -> &mut rng::RandState<R> {
         &mut self.rng
     }
+
+    pub fn bestiary(&self) -> &HashMap<String, Rc<creature::Creature>> {
+        &self.bestiary
+    }
+
+    // Adds (or replaces) every creature in a TOML bestiary, returning how many there were.
+    pub fn load_bestiary(&mut self, text: &str) -> Result<usize, bestiary::LoadError> {
+        let entries = bestiary::load(text)?;
+        let count = entries.len();
+        self.bestiary.extend(entries.into_iter().map(|(k, c)| (k, Rc::new(c))));
+        Ok(count)
+    }
+
+    pub fn square(&self, loc: space::Location) -> Option<&terrain::Square> {
+        self.map.get(loc.0)
+    }
+
+    fn square_mut(&mut self, loc: space::Location) -> Option<&mut terrain::Square> {
+        self.map.get_mut(loc.0)
+    }
+
+    pub fn creature(&self, cid: creature::CID) -> Option<&creature::State> {
+        self.creatures.get(&cid)
+    }
+
+    pub fn creature_mut(&mut self, cid: creature::CID) -> Option<&mut creature::State> {
+        self.creatures.get_mut(&cid)
+    }
+
+    pub fn creatures(&self) -> impl Iterator<Item = &creature::State> {
+        self.creatures.values()
+    }
+
+    // Whether a creature of the given size could stand with its corner at `loc`.
+    pub fn can_place(&self, size: Size, loc: space::Location) -> Result<(), SpawnError> {
+        for sq_loc in size.footprint(loc) {
+            let square = match self.square(sq_loc) {
+                Some(sq) if sq.terrain != terrain::Terrain::Unpassable => sq,
+                _ => return Err(SpawnError::Blocked(sq_loc)),
+            };
+            if let Some(&by) = square.occupants.iter().next() {
+                return Err(SpawnError::Occupied { loc: sq_loc, by });
+            }
+        }
+        Ok(())
+    }
+
+    // Instantiates a bestiary entry at full health.
+    pub fn spawn(&mut self, key: &str, side: Side, loc: space::Location) -> Result<creature::CID, SpawnError> {
+        let creature = self.bestiary.get(key)
+            .cloned()
+            .ok_or_else(|| SpawnError::UnknownCreature(key.to_string()))?;
+        self.spawn_creature(creature, side, loc)
+    }
+
+    pub fn spawn_creature(&mut self, creature: Rc<creature::Creature>, side: Side, loc: space::Location) -> Result<creature::CID, SpawnError> {
+        self.can_place(creature.size, loc)?;
+
+        let cid = creature::CID(self.next_cid);
+        self.next_cid += 1;
+        let state = creature::State::new(cid, creature, side, loc);
+        for sq_loc in state.footprint() {
+            self.square_mut(sq_loc).unwrap().occupants.insert(cid);
+        }
+        self.creatures.insert(cid, state);
+        Ok(cid)
+    }
+
+    // Takes a creature out of the world entirely; its CID is never reused. Despawning the active
+    // creature ends its turn, without end-of-turn effects, and the next creature's turn is up, to
+    // be begun with start_turn() as after end_turn().
+    pub fn despawn(&mut self, cid: creature::CID) -> Option<creature::State> {
+        self.remove_effects_where(cid, |eff| matches!(eff, Effect::Grappled { .. } | Effect::Grappling { .. }));
+        self.end_durations_of(cid);
+        let state = self.creatures.remove(&cid)?;
+        for sq_loc in state.footprint() {
+            if let Some(sq) = self.square_mut(sq_loc) {
+                sq.occupants.remove(&cid);
+            }
+        }
+        if let Some(idx) = self.initiative.iter().position(|&c| c == cid) {
+            self.initiative.remove(idx);
+            if idx < self.time.turn.0 {
+                self.time.turn.0 -= 1;
+            } else if idx == self.time.turn.0 {
+                self.pass_turn();
+            }
+        }
+        self.delaying.remove(&cid);
+        self.readied.remove(&cid);
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{RandState, Script};
+
+    // An open 10 by 10 map whose dice come from a script, which tests push to as they go.
+    pub(super) fn world() -> World<Script> {
+        let square = terrain::Square { terrain: terrain::Terrain::Passable, occupants: Default::default() };
+        WorldBuilder::new(region::Region::filled(10, 10, square), RandState::scripted(Vec::new())).build()
+    }
+
+    // A plain level 1 creature with all 10s and 20 HP, in the column `x` of the top row.
+    pub(super) fn spawn(world: &mut World<Script>, side: Side, x: isize) -> creature::CID {
+        let scores = AbilityScores::new(Abilities { _str: 10, _dex: 10, _con: 10, _int: 10, _wis: 10, _cha: 10 });
+        let creature = Creature::new("commoner", 1, scores, "N".parse().unwrap(), 20);
+        world.spawn_creature(Rc::new(creature), side, space::Location(util::V2i::new(x, 0))).unwrap()
+    }
+
+    pub(super) fn roll(world: &mut World<Script>, faces: &[usize]) {
+        for &face in faces {
+            world.rng().inner_mut().push(face);
+        }
+    }
 }
@@ -0,0 +1,158 @@
+// Spending actions. Each creature gets `Current::actions_gained` actions when its turn starts and
+// one reaction that lasts until its next turn; `act` spends from that budget and reports which
+// other creatures could react.
+
+use std::error::Error;
+use std::fmt;
+
+use super::World;
+use super::space::Squares;
+use crate::creature::{ActionTrait, Activity, Budget, CID, Cost, Current, Reaction};
+use crate::rng::Roller;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ActionError {
+    NoSuchCreature(CID),
+    NotYourTurn(CID),
+    WrongCost(Cost),
+    // Paralyzed, unconscious or dead.
+    Incapacitated(CID),
+    NotEnoughActions { needed: usize, left: usize },
+    ReactionUsed(CID),
+    // Too late to Delay: something has been spent this turn.
+    AlreadyActed(CID),
+    NoSuchAttack(usize),
+    OutOfRange { target: CID },
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionError::NoSuchCreature(cid) => write!(f, "no creature {:?}", cid),
+            ActionError::NotYourTurn(cid) => write!(f, "it isn't {:?}'s turn", cid),
+            ActionError::WrongCost(cost) => write!(f, "can't be used with cost {:?}", cost),
+            ActionError::Incapacitated(cid) => write!(f, "{:?} can't act", cid),
+            ActionError::NotEnoughActions { needed, left } => write!(f, "needs {} actions, {} left", needed, left),
+            ActionError::ReactionUsed(cid) => write!(f, "{:?} has already used its reaction", cid),
+            ActionError::AlreadyActed(cid) => write!(f, "{:?} has already acted this turn", cid),
+            ActionError::NoSuchAttack(idx) => write!(f, "no attack #{}", idx),
+            ActionError::OutOfRange { target } => write!(f, "{:?} is out of range", target),
+        }
+    }
+}
+
+impl Error for ActionError {}
+
+// A reaction some creature may now take, and what `actor` did to provoke it; pass it to
+// World::react() to take it.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Opportunity {
+    pub cid: CID,
+    pub reaction: Reaction,
+    pub actor: CID,
+    pub activity: Activity,
+}
+
+impl<R> World<R> {
+    pub fn budget(&self, cid: CID) -> Option<Budget> {
+        self.creatures.get(&cid).map(|state| state.budget)
+    }
+}
+
+impl<R: Roller> World<R> {
+    fn able(&mut self, cid: CID) -> Result<Current, ActionError> {
+        let cur = self.with_status(cid, |status, world| status.current(world))
+            .ok_or(ActionError::NoSuchCreature(cid))?;
+        if cur.paralyzed || cur.unconscious || cur.petrified || cur.dead {
+            return Err(ActionError::Incapacitated(cid));
+        }
+        Ok(cur)
+    }
+
+    // The active creature uses an action, activity or free action; free actions with a trigger
+    // can be used on anyone's turn. Returns the reactions it provokes from the other side, in CID
+    // order.
+    pub fn act(&mut self, cid: CID, act: &Activity) -> Result<Vec<Opportunity>, ActionError> {
+        if !act.cost.is_valid() {
+            return Err(ActionError::WrongCost(act.cost));
+        }
+        let triggered = act.cost == Cost::Free && act.trigger.is_some();
+        if self.active() != Some(cid) && !triggered {
+            return Err(ActionError::NotYourTurn(cid));
+        }
+        let cur = self.able(cid)?;
+
+        let needed = match act.cost {
+            Cost::Actions(n) => n,
+            Cost::Free => 0,
+            Cost::Reaction => return Err(ActionError::WrongCost(act.cost)),
+        };
+        // The quickened action is spent first on anything it's allowed for, and not otherwise.
+        let quick = match &cur.quickened {
+            Some(only) => only.contains(&act.kind),
+            None => false,
+        };
+        let budget = self.creatures[&cid].budget;
+        let left = if quick { budget.actions } else { budget.actions - budget.restricted };
+        if needed > left {
+            return Err(ActionError::NotEnoughActions { needed, left });
+        }
+        let budget = &mut self.creatures.get_mut(&cid).unwrap().budget;
+        budget.actions -= needed;
+        if quick {
+            budget.restricted = budget.restricted.saturating_sub(needed);
+        }
+        if act.has_trait(ActionTrait::Attack) {
+            budget.attacks += 1;
+        }
+
+        Ok(self.provoked(cid, act))
+    }
+
+    // Spends a creature's reaction on an opportunity, whether or not it's its turn. The reaction
+    // must be its own and triggered by the activity that provoked it.
+    pub fn react(&mut self, opp: &Opportunity) -> Result<(), ActionError> {
+        let cid = opp.cid;
+        let has = self.creatures.get(&cid)
+            .ok_or(ActionError::NoSuchCreature(cid))?
+            .creature.reactions.contains(&opp.reaction);
+        if !has || !opp.reaction.trigger.matches(&opp.activity) {
+            return Err(ActionError::WrongCost(Cost::Reaction));
+        }
+        self.able(cid)?;
+        let budget = &mut self.creatures.get_mut(&cid).unwrap().budget;
+        if !budget.reaction {
+            return Err(ActionError::ReactionUsed(cid));
+        }
+        budget.reaction = false;
+        Ok(())
+    }
+
+    fn provoked(&mut self, actor: CID, act: &Activity) -> Vec<Opportunity> {
+        let side = self.creatures[&actor].side;
+        let mut cids: Vec<CID> = self.creatures.iter()
+            .filter(|(_, state)| state.side.opposes(side))
+            .map(|(&cid, _)| cid)
+            .collect();
+        cids.sort();
+
+        let mut opps = Vec::new();
+        for cid in cids {
+            if !self.creatures[&cid].budget.reaction || self.able(cid).is_err() {
+                continue;
+            }
+            let state = &self.creatures[&cid];
+            let in_reach = match state.creature.reach() {
+                Some(reach) => state.distance(&self.creatures[&actor]) <= Squares::from(reach),
+                None => false,
+            };
+            if !in_reach {
+                continue;
+            }
+            opps.extend(state.creature.reactions.iter()
+                .filter(|r| r.trigger.matches(act))
+                .map(|r| Opportunity { cid, reaction: r.clone(), actor, activity: act.clone() }));
+        }
+        opps
+    }
+}
@@ -0,0 +1,495 @@
+// Poisons, diseases and other afflictions: a save on exposure, an optional onset, then stages
+// moved through by a save at the end of each one, until the stage drops below 1 or the maximum
+// duration runs out.
+//
+// Progress is kept in an Effect::Afflicted on the creature, and each stage's effects are added
+// with the affliction as their source, so counteracting the affliction (say, with neutralize
+// poison) removes the lot.
+
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use super::World;
+use super::time::Rounds;
+use crate::creature::*;
+use crate::rng::Roller;
+
+#[derive(Debug,Clone)]
+pub struct Stage {
+    pub effects: Vec<Effect>,
+    // Dealt each time the stage is reached, including when a save fails at the last stage.
+    pub damage: Option<DamagePacket>,
+    // How long until the next save.
+    pub interval: Rounds,
+}
+
+#[derive(Debug,Clone)]
+pub struct Affliction {
+    pub name: String,
+    pub level: isize,
+    pub save: Save,
+    pub dc: isize,
+    pub traits: Vec<EffectTrait>,
+    pub onset: Option<Rounds>,
+    pub max_duration: Option<Rounds>,
+    // Successes only reduce the stage when two come in a row, and critical successes by just 1.
+    pub virulent: bool,
+    pub stages: Vec<Stage>,
+}
+
+// Compares by identity, as DmgRef does.
+#[derive(Debug,Clone)]
+pub struct AffRef(pub Rc<Affliction>);
+
+impl PartialEq for AffRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+impl Eq for AffRef {}
+
+impl Hash for AffRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Rc::as_ptr(&self.0) as usize).hash(state);
+    }
+}
+
+// One save against an affliction, or the end of its onset. `stage` is where it ended up; 0 means
+// the creature is no longer afflicted.
+#[derive(Debug,Clone)]
+pub struct AfflictionResult {
+    pub cid: CID,
+    pub name: String,
+    pub check: Option<CheckResult>,
+    pub stage: usize,
+    pub dealt: Option<PacketResult>,
+}
+
+impl Stage {
+    pub fn new(interval: Rounds) -> Self {
+        Self { effects: Vec::new(), damage: None, interval }
+    }
+
+    pub fn with_effect(mut self, eff: Effect) -> Self {
+        self.effects.push(eff);
+        self
+    }
+
+    pub fn with_damage(mut self, damage: DamagePacket) -> Self {
+        self.damage = Some(damage);
+        self
+    }
+}
+
+impl Affliction {
+    // A Fortitude save with no stages, onset or maximum duration; fill in the rest by field or
+    // with the builders.
+    pub fn new(name: &str, level: isize, dc: isize) -> Self {
+        Self {
+            name: name.to_string(),
+            level,
+            save: Save::Fortitude,
+            dc,
+            traits: Vec::new(),
+            onset: None,
+            max_duration: None,
+            virulent: false,
+            stages: Vec::new(),
+        }
+    }
+
+    pub fn with_trait(mut self, tr: EffectTrait) -> Self {
+        self.traits.push(tr);
+        self
+    }
+
+    pub fn onset(mut self, onset: Rounds) -> Self {
+        self.onset = Some(onset);
+        self
+    }
+
+    pub fn max_duration(mut self, dur: Rounds) -> Self {
+        self.max_duration = Some(dur);
+        self
+    }
+
+    pub fn virulent(mut self) -> Self {
+        self.virulent = true;
+        self
+    }
+
+    pub fn stage(mut self, stage: Stage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    // Further exposure to a poison you're already suffering from can make it worse; diseases
+    // don't care.
+    pub fn stacks(&self) -> bool {
+        self.traits.contains(&EffectTrait::Poison)
+    }
+
+    pub fn source(self: &Rc<Self>, by: Option<CID>) -> Source {
+        let mut src = Source::new(&self.name)
+            .rank(Source::rank_of_level(self.level))
+            .dc(self.dc);
+        src.cid = by;
+        src.affliction = Some(AffRef(self.clone()));
+        self.traits.iter().fold(src, |src, &tr| src.with_trait(tr))
+    }
+
+    // The stage after a save at `stage`, and the new run of successes, for virulent afflictions.
+    fn next_stage(&self, stage: usize, degree: Degree, streak: usize) -> (usize, usize) {
+        let (down, up, streak) = match degree {
+            Degree::CriticalSuccess if self.virulent => (1, 0, 0),
+            Degree::CriticalSuccess => (2, 0, 0),
+            Degree::Success if self.virulent && streak == 0 => (0, 0, 1),
+            Degree::Success => (1, 0, 0),
+            Degree::Failure => (0, 1, 0),
+            Degree::CriticalFailure => (0, 2, 0),
+        };
+        (std::cmp::min(stage.saturating_sub(down) + up, self.stages.len()), streak)
+    }
+}
+
+impl<R: Roller> World<R> {
+    // `cid` is exposed to an affliction, perhaps by another creature. None if it's immune (or not
+    // there); a creature already afflicted only gets worse if the affliction stacks.
+    pub fn expose(&mut self, cid: CID, aff: &Rc<Affliction>, by: Option<CID>) -> Option<AfflictionResult> {
+        let source = aff.source(by);
+        if self.creatures.get(&cid)?.creature.immune_to(&source) {
+            return None;
+        }
+        let now = self.time.round;
+        let tracker = self.afflicted(cid, aff);
+        if let Some(Effect::Afflicted { stage, .. }) = &tracker {
+            if !aff.stacks() {
+                return Some(AfflictionResult { cid, name: aff.name.clone(), check: None, stage: *stage, dealt: None });
+            }
+        }
+
+        let check = self.affliction_save(cid, aff);
+        let worse = match check.degree {
+            Degree::CriticalFailure => 2,
+            Degree::Failure => 1,
+            _ => 0,
+        };
+        let (stage, dealt) = match tracker {
+            Some(Effect::Afflicted { aff: r, stage, started, next, ends, streak }) => {
+                let stage = std::cmp::min(stage + worse, aff.stages.len());
+                let (next, dealt) = if started && worse > 0 {
+                    (now + aff.stages[stage - 1].interval, self.enter_stage(cid, aff, stage, &source))
+                } else {
+                    (next, None)
+                };
+                self.track(cid, Effect::Afflicted { aff: r, stage, started, next, ends, streak }, source);
+                (stage, dealt)
+            },
+            _ if worse == 0 || aff.stages.is_empty() => (0, None),
+            _ => {
+                let stage = std::cmp::min(worse, aff.stages.len());
+                let ends = aff.max_duration.map(|dur| now + dur);
+                let (started, next, dealt) = match aff.onset {
+                    Some(onset) => (false, now + onset, None),
+                    None => (true, now + aff.stages[stage - 1].interval, self.enter_stage(cid, aff, stage, &source)),
+                };
+                self.track(cid, Effect::Afflicted { aff: AffRef(aff.clone()), stage, started, next, ends, streak: 0 }, source);
+                (stage, dealt)
+            },
+        };
+        Some(AfflictionResult { cid, name: aff.name.clone(), check: Some(check), stage, dealt })
+    }
+
+    // Ends onsets, rolls saves for stages that are up and ends afflictions that have run their
+    // course, as of the current round. Called at the start of each turn, but can be called any
+    // time after moving the clock on, in which case every save that came due in the meantime is
+    // rolled in order.
+    pub fn progress_afflictions(&mut self, cid: CID) -> Vec<AfflictionResult> {
+        let now = self.time.round;
+        let trackers: Vec<Effect> = match self.creatures.get(&cid) {
+            Some(state) if !state.status.is_dead() => state.status.effects()
+                .filter(|eff| matches!(eff, Effect::Afflicted { next, ends, .. } if *next <= now || matches!(ends, Some(e) if *e <= now)))
+                .cloned()
+                .collect(),
+            _ => return Vec::new(),
+        };
+
+        let mut results = Vec::new();
+        for tracker in trackers {
+            let (r, mut stage, mut started, mut next, ends, mut streak) = match &tracker {
+                Effect::Afflicted { aff, stage, started, next, ends, streak } => (aff.clone(), *stage, *started, *next, *ends, *streak),
+                _ => continue,
+            };
+            let aff = r.0.clone();
+            let source = self.creatures[&cid].status.source(&tracker).cloned().unwrap_or_default();
+
+            loop {
+                if matches!(ends, Some(e) if e <= now && e <= next) {
+                    self.cure(cid, &aff);
+                    results.push(AfflictionResult { cid, name: aff.name.clone(), check: None, stage: 0, dealt: None });
+                    break;
+                }
+                if next > now {
+                    break;
+                }
+
+                let check = if started {
+                    let check = self.affliction_save(cid, &aff);
+                    let (to, run) = aff.next_stage(stage, check.degree, streak);
+                    stage = to;
+                    streak = run;
+                    Some(check)
+                } else {
+                    started = true;
+                    None
+                };
+                if stage == 0 {
+                    self.cure(cid, &aff);
+                    results.push(AfflictionResult { cid, name: aff.name.clone(), check, stage, dealt: None });
+                    break;
+                }
+                let dealt = self.enter_stage(cid, &aff, stage, &source);
+                // Catching up counts from when each save was due, not from now.
+                next += std::cmp::max(aff.stages[stage - 1].interval, Rounds(1));
+                self.track(cid, Effect::Afflicted { aff: r.clone(), stage, started, next, ends, streak }, source.clone());
+                results.push(AfflictionResult { cid, name: aff.name.clone(), check, stage, dealt });
+            }
+        }
+        results
+    }
+
+    fn affliction_save(&mut self, cid: CID, aff: &Affliction) -> CheckResult {
+        let cur = self.with_status(cid, |status, world| status.current(world)).unwrap();
+        self.creatures[&cid].creature.save_check(aff.save)
+            .roller(&cur)
+            .against(&aff.name, aff.dc)
+            .roll(&mut self.rng)
+    }
+
+    // Swaps whatever stage's effects are on for those of `stage` (counting from 1), and deals its
+    // damage. `source` must be the affliction's own.
+    fn enter_stage(&mut self, cid: CID, aff: &Affliction, stage: usize, source: &Source) -> Option<PacketResult> {
+        self.remove_sourced_where(cid, |eff, src| {
+            !matches!(eff, Effect::Afflicted { .. })
+                && matches!((&src.affliction, &source.affliction), (Some(a), Some(b)) if a == b)
+        });
+
+        let st = &aff.stages[stage - 1];
+        for eff in &st.effects {
+            self.add_effect_from(cid, eff.clone(), source.clone());
+        }
+        let packet = st.damage.as_ref()?;
+        let results: Vec<DamageResult> = packet.roll(&mut self.rng).iter().map(|r| r.result).collect();
+        let dealt = self.deal_damage(cid, &results, false);
+        self.add_persistent(cid, packet.persistent.clone(), source);
+        dealt
+    }
+}
+
+impl<R> World<R> {
+    // The creature's progress through an affliction, if it has it.
+    pub fn afflicted(&self, cid: CID, aff: &Rc<Affliction>) -> Option<Effect> {
+        self.creatures.get(&cid)?.status.effects()
+            .find(|eff| matches!(eff, Effect::Afflicted { aff: r, .. } if Rc::ptr_eq(&r.0, aff)))
+            .cloned()
+    }
+
+    // Ends an affliction and everything its stages caused; its progress is sourced from it too.
+    pub fn cure(&mut self, cid: CID, aff: &Rc<Affliction>) -> Vec<Effect> {
+        self.remove_sourced_where(cid, |_, src| matches!(&src.affliction, Some(r) if Rc::ptr_eq(&r.0, aff)))
+            .into_iter()
+            .map(|(eff, _)| eff)
+            .collect()
+    }
+
+    // Replaces the progress tracker for the affliction in `tracker`.
+    fn track(&mut self, cid: CID, tracker: Effect, source: Source) {
+        let now = self.time.round;
+        if let Some(state) = self.creatures.get_mut(&cid) {
+            if let Some(old) = state.status.get(&tracker).cloned() {
+                state.status.remove(&old);
+            }
+            state.status.add_from(tracker, source, now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::time::Round;
+    use crate::world::tests::{roll, spawn, world};
+
+    // Fortitude saves against this come out as: 20 a critical success, 15 a success, 10 a
+    // failure and 1 a critical failure.
+    const DC: isize = 15;
+
+    const CRIT_SUCCESS: usize = 20;
+    const SUCCESS: usize = 15;
+    const FAILURE: usize = 10;
+    const CRIT_FAILURE: usize = 1;
+
+    // Three stages of enfeebled 1, 2 and 3, with a save every `interval` rounds.
+    fn poison(interval: usize) -> Affliction {
+        (1 ..= 3).fold(Affliction::new("spider venom", 1, DC).with_trait(EffectTrait::Poison), |aff, level| {
+            aff.stage(Stage::new(Rounds(interval)).with_effect(Effect::Enfeebled { level }))
+        })
+    }
+
+    fn stage<R>(w: &World<R>, cid: CID, aff: &Rc<Affliction>) -> usize {
+        match w.afflicted(cid, aff) {
+            Some(Effect::Afflicted { stage, .. }) => stage,
+            _ => 0,
+        }
+    }
+
+    fn enfeebled<R>(w: &World<R>, cid: CID) -> Vec<usize> {
+        w.creature(cid).unwrap().status.effects()
+            .filter_map(|eff| match eff { Effect::Enfeebled { level } => Some(*level), _ => None })
+            .collect()
+    }
+
+    fn stages(results: &[AfflictionResult]) -> Vec<usize> {
+        results.iter().map(|r| r.stage).collect()
+    }
+
+    #[test]
+    fn onset() {
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        assert_eq!(w.creature(a).unwrap().creature.save(Save::Fortitude), 0);
+        let aff = Rc::new(poison(1).onset(Rounds(2)));
+
+        roll(&mut w, &[FAILURE]);
+        assert_eq!(w.expose(a, &aff, None).unwrap().stage, 1);
+        assert!(enfeebled(&w, a).is_empty());
+        w.time.round = Round(1);
+        assert!(w.progress_afflictions(a).is_empty());
+
+        // The onset ends without a save.
+        w.time.round = Round(2);
+        let results = w.progress_afflictions(a);
+        assert_eq!(stages(&results), vec![1]);
+        assert!(results[0].check.is_none());
+        assert_eq!(enfeebled(&w, a), vec![1]);
+
+        roll(&mut w, &[FAILURE]);
+        w.time.round = Round(3);
+        assert_eq!(stages(&w.progress_afflictions(a)), vec![2]);
+        assert_eq!(enfeebled(&w, a), vec![2]);
+
+        roll(&mut w, &[CRIT_SUCCESS]);
+        w.time.round = Round(4);
+        assert_eq!(stages(&w.progress_afflictions(a)), vec![0]);
+        assert!(enfeebled(&w, a).is_empty());
+        assert!(w.afflicted(a, &aff).is_none());
+    }
+
+    #[test]
+    fn intervals() {
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        let aff = Rc::new(poison(2));
+
+        roll(&mut w, &[CRIT_FAILURE]);
+        assert_eq!(w.expose(a, &aff, None).unwrap().stage, 2);
+        assert_eq!(enfeebled(&w, a), vec![2]);
+        w.time.round = Round(1);
+        assert!(w.progress_afflictions(a).is_empty());
+
+        roll(&mut w, &[SUCCESS]);
+        w.time.round = Round(2);
+        assert_eq!(stages(&w.progress_afflictions(a)), vec![1]);
+        assert_eq!(enfeebled(&w, a), vec![1]);
+    }
+
+    #[test]
+    fn catch_up() {
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        let aff = Rc::new(poison(2));
+
+        roll(&mut w, &[FAILURE]);
+        w.expose(a, &aff, None).unwrap();
+        // Saves were due in rounds 2, 4 and 6, and are rolled in that order.
+        roll(&mut w, &[FAILURE, SUCCESS, CRIT_FAILURE]);
+        w.time.round = Round(7);
+        let results = w.progress_afflictions(a);
+        assert_eq!(stages(&results), vec![2, 1, 3]);
+        assert!(results.iter().all(|r| r.check.is_some()));
+        assert_eq!(w.rng().inner().remaining(), 0);
+        assert_eq!(enfeebled(&w, a), vec![3]);
+        match w.afflicted(a, &aff) {
+            Some(Effect::Afflicted { next, .. }) => assert_eq!(next, Round(8)),
+            other => panic!("not afflicted: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn virulent() {
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        let aff = Rc::new(poison(1).virulent());
+
+        roll(&mut w, &[CRIT_FAILURE]);
+        w.expose(a, &aff, None).unwrap();
+        // One success does nothing, the second in a row reduces the stage by 1, and so does a
+        // critical success.
+        roll(&mut w, &[SUCCESS, SUCCESS, SUCCESS, FAILURE, SUCCESS, CRIT_SUCCESS]);
+        w.time.round = Round(6);
+        assert_eq!(stages(&w.progress_afflictions(a)), vec![2, 1, 1, 2, 2, 1]);
+    }
+
+    #[test]
+    fn exposures() {
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        let aff = Rc::new(poison(1));
+
+        roll(&mut w, &[FAILURE, FAILURE]);
+        w.expose(a, &aff, None).unwrap();
+        // Poisons stack: failing again moves it on a stage.
+        assert_eq!(w.expose(a, &aff, None).unwrap().stage, 2);
+        assert_eq!(enfeebled(&w, a), vec![2]);
+        roll(&mut w, &[SUCCESS]);
+        assert_eq!(w.expose(a, &aff, None).unwrap().stage, 2);
+
+        // Diseases don't; further exposure is no save and no change.
+        let b = spawn(&mut w, Side::Party, 2);
+        let disease = Rc::new(Affliction { traits: vec![EffectTrait::Disease], ..poison(1) });
+        roll(&mut w, &[FAILURE]);
+        w.expose(b, &disease, None).unwrap();
+        let again = w.expose(b, &disease, None).unwrap();
+        assert!(again.check.is_none());
+        assert_eq!(again.stage, 1);
+
+        // Two afflictions are tracked apart, and curing one leaves the other.
+        assert_eq!(stage(&w, b, &disease), 1);
+        assert_eq!(stage(&w, b, &aff), 0);
+        roll(&mut w, &[CRIT_FAILURE]);
+        w.expose(b, &aff, None).unwrap();
+        w.cure(b, &disease);
+        assert_eq!(stage(&w, b, &disease), 0);
+        assert_eq!(stage(&w, b, &aff), 2);
+        assert_eq!(enfeebled(&w, b), vec![2]);
+    }
+
+    #[test]
+    fn max_duration() {
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        let aff = Rc::new(poison(1).max_duration(Rounds(3)));
+
+        roll(&mut w, &[FAILURE]);
+        w.expose(a, &aff, None).unwrap();
+        // Saves in rounds 1 and 2; it ends in round 3 before the save that would be due then.
+        roll(&mut w, &[FAILURE, FAILURE]);
+        w.time.round = Round(5);
+        let results = w.progress_afflictions(a);
+        assert_eq!(stages(&results), vec![2, 3, 0]);
+        assert!(results[2].check.is_none());
+        assert_eq!(w.rng().inner().remaining(), 0);
+        assert!(enfeebled(&w, a).is_empty());
+        assert!(w.afflicted(a, &aff).is_none());
+    }
+}
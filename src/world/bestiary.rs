@@ -0,0 +1,546 @@
+// Loading stat blocks from TOML. Each top-level table is one creature, keyed by the name it is
+// spawned under:
+//
+//   [goblin-warrior]
+//   name = "Goblin Warrior"
+//   level = -1
+//   size = "small"
+//   alignment = "CE"
+//   traits = ["goblin", "humanoid"]
+//   hp = 6
+//   abilities = { str = 0, dex = 3, con = 1, int = 0, wis = -1, cha = 1 }
+//   armor = { prof = "trained", item = 2, dex-cap = 3 }
+//   perception = "trained"
+//   saves = { fortitude = "trained", reflex = "expert" }
+//   skills = { acrobatics = "trained", stealth = "trained" }
+//   speeds = { land = 25 }
+//   senses = ["darkvision"]
+//   languages = ["Common", "Goblin"]
+//   resistances = [{ against = "physical", amount = 5, except = ["silver"] }]
+//   weaknesses = { cold-iron = 3 }
+//   immunities = ["poison", "mental"]
+//
+//   [[goblin-warrior.attacks]]
+//   name = "dogslicer"
+//   range = "melee"
+//   traits = ["agile", "finesse"]
+//   damage = [{ amount = "1d6", type = "slashing" }]
+//
+// `reactions` lists standard reactions by name, e.g. ["attack-of-opportunity"].
+//
+// Abilities are given as modifiers, as printed in stat blocks. Omitted proficiencies are
+// untrained, except armor, which defaults to trained.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::Deserialize;
+use serde::de::{DeserializeOwned, IntoDeserializer};
+
+use crate::creature::*;
+use crate::rng::RandValue;
+use crate::world::space::Feet;
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct LoadError {
+    // 1-based; None if the location couldn't be determined.
+    pub line: Option<usize>,
+    // Dotted path to the offending value, e.g. "goblin.attacks[0].damage".
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}: {}", line, self.field, self.message),
+            None => write!(f, "{}: {}", self.field, self.message),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Entry {
+    name: Option<String>,
+    level: isize,
+    #[serde(default = "medium")]
+    size: Size,
+    alignment: String,
+    #[serde(default)]
+    traits: Vec<Trait>,
+    hp: usize,
+    abilities: AbilityEntry,
+    #[serde(default)]
+    armor: ArmorEntry,
+    #[serde(default = "untrained")]
+    perception: Proficiency,
+    #[serde(default)]
+    saves: BTreeMap<String, Proficiency>,
+    #[serde(default)]
+    skills: BTreeMap<String, Proficiency>,
+    #[serde(default)]
+    speeds: BTreeMap<String, usize>,
+    #[serde(default)]
+    senses: Vec<String>,
+    #[serde(default)]
+    languages: Vec<String>,
+    #[serde(default)]
+    resistances: Vec<ResistanceEntry>,
+    #[serde(default)]
+    weaknesses: BTreeMap<String, usize>,
+    #[serde(default)]
+    immunities: Vec<String>,
+    #[serde(default)]
+    attacks: Vec<AttackEntry>,
+    #[serde(default)]
+    reactions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AbilityEntry {
+    str: isize,
+    dex: isize,
+    con: isize,
+    int: isize,
+    wis: isize,
+    cha: isize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct ArmorEntry {
+    #[serde(default = "trained")]
+    prof: Proficiency,
+    #[serde(default)]
+    item: isize,
+    dex_cap: Option<isize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResistanceEntry {
+    against: String,
+    amount: usize,
+    #[serde(default)]
+    except: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+enum RangeEntry {
+    Melee,
+    Ranged,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AttackEntry {
+    name: String,
+    range: RangeEntry,
+    // Reach for melee attacks (default 5), range increment for ranged ones (required).
+    reach: Option<usize>,
+    increment: Option<usize>,
+    #[serde(default = "trained")]
+    prof: Proficiency,
+    #[serde(default)]
+    item: isize,
+    #[serde(default)]
+    traits: Vec<String>,
+    damage: Vec<DamageEntry>,
+    #[serde(default)]
+    persistent: Vec<DamageEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DamageEntry {
+    amount: RandValue,
+    #[serde(rename = "type")]
+    tp: DamageType,
+    #[serde(default)]
+    magical: bool,
+    material: Option<Material>,
+    precision: Option<RandValue>,
+}
+
+fn medium() -> Size { Size::Medium }
+fn untrained() -> Proficiency { Proficiency::Untrained }
+fn trained() -> Proficiency { Proficiency::Trained }
+
+impl Default for ArmorEntry {
+    fn default() -> Self {
+        Self { prof: trained(), item: 0, dex_cap: None }
+    }
+}
+
+// "a . \"b\"" to "a.b".
+fn dotted(key: &str) -> String {
+    key.split('.').map(|k| k.trim().trim_matches('"')).collect::<Vec<_>>().join(".")
+}
+
+// The line each key and table in the file first appears on, by dotted path as in
+// LoadError::field, e.g. "goblin.attacks[0].damage". This only looks at the start of each line,
+// so anything inside an inline table or array goes by the line of the key that holds it.
+fn key_lines(text: &str) -> HashMap<String, usize> {
+    let mut lines = HashMap::new();
+    let mut arrays: HashMap<String, usize> = HashMap::new();
+    let mut table = String::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let path = if let Some(header) = line.strip_prefix("[[") {
+            let path = dotted(header.split("]]").next().unwrap_or(""));
+            let count = arrays.entry(path.clone()).or_insert(0);
+            table = format!("{}[{}]", path, count);
+            *count += 1;
+            table.clone()
+        } else if let Some(header) = line.strip_prefix('[') {
+            table = dotted(header.split(']').next().unwrap_or(""));
+            table.clone()
+        } else {
+            match line.split_once('=') {
+                Some((key, _)) if !key.trim().is_empty() && !key.contains(|c| "{}[],#".contains(c)) => {
+                    if table.is_empty() { dotted(key) } else { format!("{}.{}", table, dotted(key)) }
+                },
+                _ => continue,
+            }
+        };
+        // A creature given only as subtables starts where the first of them does.
+        for (cut, _) in path.match_indices('.') {
+            lines.entry(path[.. cut].to_string()).or_insert(idx + 1);
+        }
+        lines.entry(path).or_insert(idx + 1);
+    }
+    lines
+}
+
+// The line `field` was given on, or failing that the nearest enclosing key or table.
+fn field_line(text: &str, field: &str) -> Option<usize> {
+    let lines = key_lines(text);
+    let mut path = field;
+    loop {
+        if let Some(&line) = lines.get(path) {
+            return Some(line);
+        }
+        path = &path[.. path.rfind(['.', '['])?];
+    }
+}
+
+// Parses a kebab-case name into any of the enums that deserialize from one.
+fn named<T: DeserializeOwned>(s: &str) -> Option<T> {
+    T::deserialize(IntoDeserializer::<serde::de::value::Error>::into_deserializer(s)).ok()
+}
+
+// Damage types win over kinds where the names collide ("mental", "poison").
+fn spec(s: &str) -> Option<Spec> {
+    match s {
+        "magical" => Some(Spec::Magical),
+        "non-magical" => Some(Spec::NonMagical),
+        "precision" => Some(Spec::Precision),
+        "all" => Some(Spec::All),
+        _ => named(s).map(Spec::Type)
+            .or_else(|| named(s).map(Spec::Kind))
+            .or_else(|| named(s).map(Spec::Material)),
+    }
+}
+
+struct Validator<'a> {
+    text: &'a str,
+    key: &'a str,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&self, field: &str, message: String) -> LoadError {
+        let field = format!("{}.{}", self.key, field);
+        LoadError { line: field_line(self.text, &field), field, message }
+    }
+
+    fn spec(&self, field: &str, s: &str) -> Result<Spec, LoadError> {
+        spec(s).ok_or_else(|| self.error(field, format!("unknown damage type or category {:?}", s)))
+    }
+
+    // TOML only ever hands out table keys as strings, so enum-keyed tables go through here.
+    fn keyed<K, V>(&self, field: &str, map: BTreeMap<String, V>, what: &str) -> Result<HashMap<K, V>, LoadError>
+        where K: DeserializeOwned + Eq + std::hash::Hash
+    {
+        map.into_iter()
+            .map(|(name, v)| match named(&name) {
+                Some(k) => Ok((k, v)),
+                None => Err(self.error(&format!("{}.{}", field, name), format!("unknown {} {:?}", what, name))),
+            })
+            .collect()
+    }
+
+    fn damage(&self, field: &str, d: DamageEntry) -> Result<Damage, LoadError> {
+        if !matches!(d.amount.max(), Some(max) if max > 0) {
+            return Err(self.error(field, format!("damage {} can never be positive", d.amount)));
+        }
+        Ok(Damage {
+            tp: d.tp,
+            magical: d.magical,
+            material: d.material,
+            amount: d.amount,
+            prec_amount: d.precision,
+        })
+    }
+
+    fn attack(&self, field: &str, a: AttackEntry) -> Result<Attack, LoadError> {
+        let range = match (a.range, a.reach, a.increment) {
+            (RangeEntry::Melee, reach, None) => AttackRange::Melee { reach: Feet(reach.unwrap_or(5)) },
+            (RangeEntry::Ranged, None, Some(0)) => {
+                return Err(self.error(&format!("{}.increment", field), "range increment must be positive".to_string()));
+            },
+            (RangeEntry::Ranged, None, Some(increment)) => AttackRange::Ranged { increment: Feet(increment) },
+            (RangeEntry::Melee, _, Some(_)) => {
+                return Err(self.error(&format!("{}.increment", field), "melee attacks have a reach, not an increment".to_string()));
+            },
+            (RangeEntry::Ranged, Some(_), _) => {
+                return Err(self.error(&format!("{}.reach", field), "ranged attacks have an increment, not a reach".to_string()));
+            },
+            (RangeEntry::Ranged, None, None) => {
+                return Err(self.error(field, "ranged attacks need an increment".to_string()));
+            },
+        };
+        if a.damage.is_empty() {
+            return Err(self.error(&format!("{}.damage", field), "an attack must deal some damage".to_string()));
+        }
+        let components = a.damage.into_iter()
+            .enumerate()
+            .map(|(idx, d)| self.damage(&format!("{}.damage[{}]", field, idx), d))
+            .collect::<Result<Vec<_>, _>>()?;
+        let persistent = a.persistent.into_iter()
+            .enumerate()
+            .map(|(idx, d)| self.damage(&format!("{}.persistent[{}]", field, idx), d))
+            .collect::<Result<Vec<_>, _>>()?;
+        let traits = a.traits.iter()
+            .map(|tr| tr.parse().map_err(|msg| self.error(&format!("{}.traits", field), msg)))
+            .collect::<Result<_, _>>()?;
+        Ok(Attack {
+            name: a.name,
+            range,
+            prof: a.prof,
+            item_bonus: a.item,
+            traits,
+            damage: DamagePacket { components, persistent, splash: None },
+        })
+    }
+
+    fn creature(&self, e: Entry) -> Result<Creature, LoadError> {
+        if e.level < -1 || e.level > 25 {
+            return Err(self.error("level", format!("level {} is outside -1 ..= 25", e.level)));
+        }
+        if e.hp == 0 {
+            return Err(self.error("hp", "a creature needs at least 1 hit point".to_string()));
+        }
+        let align = e.alignment.parse().map_err(|msg| self.error("alignment", msg))?;
+
+        let ab = e.abilities;
+        let mods = [ab.str, ab.dex, ab.con, ab.int, ab.wis, ab.cha];
+        if let Some(m) = mods.iter().find(|m| m.abs() > 10) {
+            return Err(self.error("abilities", format!("modifier {:+} is out of range", m)));
+        }
+        let score = |m: isize| 10 + 2 * m;
+        let scores = AbilityScores::new(Abilities {
+            _str: score(ab.str),
+            _dex: score(ab.dex),
+            _con: score(ab.con),
+            _int: score(ab.int),
+            _wis: score(ab.wis),
+            _cha: score(ab.cha),
+        });
+
+        if let Some(cap) = e.armor.dex_cap {
+            if cap < 0 {
+                return Err(self.error("armor.dex-cap", format!("negative dex cap {}", cap)));
+            }
+        }
+
+        let mut creature = Creature::new(e.name.as_deref().unwrap_or(self.key), e.level, scores, align, e.hp);
+        creature.size = e.size;
+        creature.traits = e.traits.into_iter().collect();
+        creature.armor = Armor { prof: e.armor.prof, item_bonus: e.armor.item, dex_cap: e.armor.dex_cap };
+        creature.perception = e.perception;
+        creature.saves = self.keyed("saves", e.saves, "save")?;
+        creature.skills = self.keyed("skills", e.skills, "skill")?;
+        if !e.speeds.is_empty() {
+            creature.speeds = self.keyed("speeds", e.speeds, "speed")?
+                .into_iter()
+                .map(|(tp, ft)| (tp, Feet(ft)))
+                .collect();
+        }
+        creature.senses = e.senses.iter()
+            .map(|s| s.parse().map_err(|msg| self.error("senses", msg)))
+            .collect::<Result<_, _>>()?;
+        creature.languages = e.languages;
+
+        for (idx, r) in e.resistances.into_iter().enumerate() {
+            let field = format!("resistances[{}]", idx);
+            if r.amount == 0 {
+                return Err(self.error(&field, "resistance amount must be positive".to_string()));
+            }
+            let mut res = Resistance::new(r.amount);
+            for ex in &r.except {
+                res = res.except(self.spec(&field, ex)?);
+            }
+            let against = self.spec(&field, &r.against)?;
+            if creature.dmgmods.resistances.insert(against, res).is_some() {
+                return Err(self.error(&field, format!("more than one resistance to {:?}", r.against)));
+            }
+        }
+        for (name, amount) in e.weaknesses {
+            let field = format!("weaknesses.{}", name);
+            if amount == 0 {
+                return Err(self.error(&field, "weakness amount must be positive".to_string()));
+            }
+            if creature.dmgmods.weaknesses.insert(self.spec(&field, &name)?, amount).is_some() {
+                return Err(self.error(&field, format!("more than one weakness to {:?}", name)));
+            }
+        }
+        // Some names, like poison, are both a damage type and an effect trait.
+        for name in &e.immunities {
+            let tr: Option<EffectTrait> = named(name);
+            let sp = spec(name);
+            if tr.is_none() && sp.is_none() {
+                return Err(self.error("immunities", format!("unknown damage type, category or effect trait {:?}", name)));
+            }
+            if let Some(tr) = tr {
+                creature.effect_immunities.insert(tr);
+            }
+            if let Some(sp) = sp {
+                creature.dmgmods.immunities.insert(sp);
+            }
+        }
+
+        creature.attacks = e.attacks.into_iter()
+            .enumerate()
+            .map(|(idx, a)| self.attack(&format!("attacks[{}]", idx), a))
+            .collect::<Result<_, _>>()?;
+        creature.reactions = e.reactions.iter()
+            .map(|name| Reaction::named(name).ok_or_else(|| self.error("reactions", format!("unknown reaction {:?}", name))))
+            .collect::<Result<_, _>>()?;
+        Ok(creature)
+    }
+}
+
+pub fn load(text: &str) -> Result<HashMap<String, Creature>, LoadError> {
+    let mut de = toml::Deserializer::new(text);
+    let entries: BTreeMap<String, Entry> = serde_path_to_error::deserialize(&mut de)
+        .map_err(|err| {
+            let field = err.path().to_string();
+            let inner = err.into_inner();
+            // The message ends with the position when there is one; that goes in `line` instead.
+            let mut message = inner.to_string();
+            if let Some((line, col)) = inner.line_col() {
+                let at = format!(" at line {} column {}", line + 1, col + 1);
+                if message.ends_with(&at) {
+                    message.truncate(message.len() - at.len());
+                }
+            }
+            // The parser only knows where the table is for some errors, like unknown fields, so a
+            // key of our own goes first.
+            let line = key_lines(text).get(&field).copied()
+                .or_else(|| inner.line_col().map(|(line, _)| line + 1))
+                .or_else(|| field_line(text, &field));
+            LoadError { line, field, message }
+        })?;
+
+    entries.into_iter()
+        .map(|(key, entry)| {
+            let creature = Validator { text, key: &key }.creature(entry)?;
+            Ok((key, creature))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOBLINS: &str = r#"[goblin-warrior]
+name = "Goblin Warrior"
+level = -1
+size = "small"
+alignment = "CE"
+hp = 6
+abilities = { str = 0, dex = 3, con = 1, int = 0, wis = -1, cha = 1 }
+armor = { item = 2, dex-cap = 3 }
+speeds = { land = 25 }
+
+[[goblin-warrior.attacks]]
+name = "dogslicer"
+range = "melee"
+traits = ["agile", "finesse"]
+damage = [{ amount = "1d6", type = "slashing" }]
+
+[goblin-pyro]
+level = 1
+alignment = "CE"
+hp = 15
+abilities = { str = 0, dex = 3, con = 1, int = 0, wis = -1, cha = 1 }
+"#;
+
+    fn error(find: &str, replace: &str) -> LoadError {
+        assert!(GOBLINS.contains(find));
+        load(&GOBLINS.replacen(find, replace, 1)).unwrap_err()
+    }
+
+    #[test]
+    fn lines() {
+        let lines = key_lines(GOBLINS);
+        assert_eq!(lines["goblin-warrior"], 1);
+        assert_eq!(lines["goblin-warrior.speeds"], 9);
+        assert_eq!(lines["goblin-warrior.attacks[0]"], 11);
+        assert_eq!(lines["goblin-warrior.attacks[0].damage"], 15);
+        assert_eq!(lines["goblin-pyro.hp"], 20);
+        // Inside an inline table or array goes by the key holding it.
+        assert_eq!(field_line(GOBLINS, "goblin-warrior.attacks[0].damage[0].amount"), Some(15));
+        assert_eq!(field_line(GOBLINS, "goblin-pyro.skills"), Some(17));
+        assert_eq!(field_line(GOBLINS, "kobold"), None);
+    }
+
+    #[test]
+    fn valid() {
+        let creatures = load(GOBLINS).unwrap();
+        let warrior = &creatures["goblin-warrior"];
+        assert_eq!(warrior.name, "Goblin Warrior");
+        assert_eq!(warrior.size, Size::Small);
+        assert_eq!(warrior.ac(), 10 + 3 + 1 + 2);
+        assert_eq!(warrior.attacks.len(), 1);
+        assert_eq!(warrior.attacks[0].name, "dogslicer");
+        assert_eq!(creatures["goblin-pyro"].name, "goblin-pyro");
+    }
+
+    #[test]
+    fn unknown_field() {
+        let err = error("hp = 15\n", "hp = 15\nmana = 3\n");
+        assert_eq!(err.field, "goblin-pyro.mana");
+        assert!(err.message.contains("mana"), "{}", err);
+        assert_eq!(err.line, Some(21));
+    }
+
+    #[test]
+    fn bad_dice() {
+        let err = error("\"1d6\"", "\"1d\"");
+        assert!(err.field.starts_with("goblin-warrior.attacks[0].damage"), "{}", err);
+        assert!(!err.message.contains(" at line "), "{}", err);
+        assert_eq!(err.line, Some(15));
+    }
+
+    #[test]
+    fn invalid_value() {
+        let err = error("hp = 15", "hp = 0");
+        assert_eq!(err.field, "goblin-pyro.hp");
+        assert_eq!(err.line, Some(20));
+
+        let err = error("land = 25", "lnad = 25");
+        assert_eq!(err.field, "goblin-warrior.speeds.lnad");
+        assert_eq!(err.line, Some(9));
+
+        let err = error("range = \"melee\"", "range = \"melee\"\nincrement = 30");
+        assert_eq!(err.field, "goblin-warrior.attacks[0].increment");
+        assert_eq!(err.line, Some(14));
+    }
+}
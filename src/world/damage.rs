@@ -0,0 +1,64 @@
+// Where damage and healing meet Health and the dying rules.
+
+use std::rc::Rc;
+
+use super::World;
+use crate::creature::*;
+
+impl<R> World<R> {
+    // Applies already-rolled damage through the target's weaknesses, resistances and immunities.
+    // A living creature brought to 0 HP starts dying (or dies further, if it already was); other
+    // creatures are destroyed outright. `crit` is for critical hits and critically failed saves.
+    pub fn deal_damage(&mut self, cid: CID, results: &[DamageResult], crit: bool) -> Option<PacketResult> {
+        let now = self.time.round;
+        let state = self.creatures.get_mut(&cid)?;
+        let dealt = state.creature.dmgmods.apply_all(results, &state.creature.target());
+        if dealt.total == 0 || state.status.is_dead() {
+            return Some(dealt);
+        }
+
+        state.health.take_damage(dealt.total);
+        if state.health.is_down() {
+            if state.creature.vitality() != Vitality::Living {
+                state.status.die();
+            } else if state.status.dying() > 0 {
+                state.status.worsen_dying(crit);
+            } else {
+                state.status.knock_out(crit, now);
+            }
+        }
+        Some(dealt)
+    }
+
+    // Attaches persistent damage as effects from `source`, skipping any the target can't be hurt
+    // by, is immune to the source of or already has worse of. Returns what was attached.
+    pub fn add_persistent(&mut self, cid: CID, damage: Vec<Damage>, source: &Source) -> Vec<Damage> {
+        let creature = match self.creatures.get(&cid) {
+            Some(state) => state.creature.clone(),
+            None => return Vec::new(),
+        };
+        let tgt = creature.target();
+        damage.into_iter()
+            .filter(|d| d.tp.affects(&tgt) && !creature.dmgmods.immune(d))
+            .filter(|d| {
+                let eff = Effect::PersistentDamage { dmg: DmgRef(Rc::new(d.clone())) };
+                self.add_effect_from(cid, eff, source.clone())
+            })
+            .collect()
+    }
+
+    // Returns how much was actually restored. Healing from 0 HP ends dying (leaving the creature
+    // wounded) and wakes it up; the dead stay dead.
+    pub fn heal(&mut self, cid: CID, amount: usize) -> usize {
+        let state = match self.creatures.get_mut(&cid) {
+            Some(state) if !state.status.is_dead() => state,
+            _ => return 0,
+        };
+        let before = state.health.hp;
+        state.health.take_healing(amount);
+        if before == 0 && state.health.hp > 0 {
+            state.status.revive();
+        }
+        state.health.hp - before
+    }
+}
@@ -0,0 +1,200 @@
+// Adding, removing, expiring and counteracting effects on creatures in the world, keeping effects
+// that involve two creatures (grappling) consistent on both, and maximum HP in step with drained.
+
+use super::World;
+use super::action::ActionError;
+use super::time::Hook;
+use crate::creature::*;
+use crate::rng::Roller;
+
+impl<R> World<R> {
+    // See Status::add(); returns whether anything changed.
+    pub fn add_effect(&mut self, cid: CID, eff: Effect) -> bool {
+        self.add_effect_from(cid, eff, Source::default())
+    }
+
+    // As add_effect(), from a known source. Nothing happens if the creature is immune to any of the
+    // source's traits or the effect's own.
+    pub fn add_effect_from(&mut self, cid: CID, eff: Effect, mut source: Source) -> bool {
+        for &tr in eff.traits() {
+            source = source.with_trait(tr);
+        }
+        match self.creatures.get(&cid) {
+            Some(state) if state.creature.immune_to(&source) => return false,
+            _ => (),
+        }
+
+        let now = self.time.round;
+        let partner = match eff {
+            Effect::Grappled { holder } => Some((holder, Effect::Grappling { holding: cid })),
+            Effect::Grappling { holding } => Some((holding, Effect::Grappled { holder: cid })),
+            _ => None,
+        };
+        if let Some((other, _)) = partner {
+            if other == cid || !self.creatures.contains_key(&other) {
+                return false;
+            }
+        }
+
+        let (changed, drained) = match self.creatures.get_mut(&cid) {
+            Some(state) => {
+                let drained = state.status.drained();
+                (state.status.add_from(eff, source.clone(), now), drained)
+            },
+            None => return false,
+        };
+        if let Some((other, linked)) = partner {
+            self.creatures.get_mut(&other).unwrap().status.add_from(linked, source, now);
+        }
+        self.drain(cid, drained);
+        changed
+    }
+
+    pub fn remove_effect(&mut self, cid: CID, eff: &Effect) -> bool {
+        let (removed, drained) = match self.creatures.get_mut(&cid) {
+            Some(state) => (state.status.remove(eff), state.status.drained()),
+            None => return false,
+        };
+        if removed {
+            self.unlink(cid, eff);
+            self.drain(cid, drained);
+        }
+        removed
+    }
+
+    // Removes everything matching from one creature, along with the other side of any grapple.
+    pub fn remove_effects_where<F: FnMut(&Effect) -> bool>(&mut self, cid: CID, mut pred: F) -> Vec<Effect> {
+        self.remove_sourced_where(cid, |eff, _| pred(eff)).into_iter().map(|(eff, _)| eff).collect()
+    }
+
+    // As remove_effects_where(), by source as well.
+    pub fn remove_sourced_where<F: FnMut(&Effect, &Source) -> bool>(&mut self, cid: CID, pred: F) -> Vec<(Effect, Source)> {
+        let (removed, drained) = match self.creatures.get_mut(&cid) {
+            Some(state) => (state.status.remove_sourced_where(pred), state.status.drained()),
+            None => return Vec::new(),
+        };
+        for (eff, _) in &removed {
+            self.unlink(cid, eff);
+        }
+        self.drain(cid, drained);
+        removed
+    }
+
+    // Ends and counts down effects at the `hook` of `turn_of`'s turn, on every creature, since
+    // some last until another creature's turn. Returns what ended, by creature.
+    pub fn expire(&mut self, turn_of: CID, hook: Hook) -> Vec<(CID, Vec<Effect>)> {
+        let now = self.time.round;
+        let mut cids: Vec<CID> = self.creatures.keys().copied().collect();
+        cids.sort();
+        let mut ended = Vec::new();
+        for cid in cids {
+            let (gone, drained) = match self.creatures.get_mut(&cid) {
+                Some(state) => {
+                    let drained = state.status.drained();
+                    (state.status.expire(turn_of, cid == turn_of, hook, now), drained)
+                },
+                None => continue,
+            };
+            if gone.is_empty() {
+                continue;
+            }
+            for eff in &gone {
+                self.unlink(cid, eff);
+            }
+            self.drain(cid, drained);
+            ended.push((cid, gone));
+        }
+        ended
+    }
+
+    // Ends everything whose duration is counted in `of`'s turns, e.g. once it leaves the fight.
+    pub(super) fn end_durations_of(&mut self, of: CID) {
+        let cids: Vec<CID> = self.creatures.keys().copied().collect();
+        for cid in cids {
+            self.remove_effects_where(cid, |eff| eff.lasts().and_then(|l| l.of()) == Some(of));
+        }
+    }
+
+    // Keeps maximum HP in step with drained: each point costs the creature's level (at least 1) in
+    // maximum HP, and that many HP when it's gained. `before` is the drained value beforehand.
+    fn drain(&mut self, cid: CID, before: usize) {
+        let state = match self.creatures.get_mut(&cid) {
+            Some(state) => state,
+            None => return,
+        };
+        let after = state.status.drained();
+        let per = std::cmp::max(state.creature.level, 1) as usize;
+        state.health.max_hp = std::cmp::max(state.creature.max_hp.saturating_sub(after * per), 1);
+        let lost = after.saturating_sub(before) * per;
+        state.health.hp = std::cmp::min(state.health.hp.saturating_sub(lost), state.health.max_hp);
+    }
+
+    fn unlink(&mut self, cid: CID, eff: &Effect) {
+        let (other, linked) = match *eff {
+            Effect::Grappled { holder } => (holder, Effect::Grappling { holding: cid }),
+            Effect::Grappling { holding } => (holding, Effect::Grappled { holder: cid }),
+            _ => return,
+        };
+        if let Some(state) = self.creatures.get_mut(&other) {
+            state.status.remove(&linked);
+        }
+    }
+}
+
+// One counteract check, against every matching effect from one source.
+#[derive(Debug,Clone)]
+pub struct CounteractResult {
+    pub source: Source,
+    pub check: CheckResult,
+    pub removed: Vec<Effect>,
+}
+
+impl<R: Roller> World<R> {
+    // Tries to counteract each source of matching effects on `cid` in turn, removing its matching
+    // effects on success. Sources without a DC can't be counteracted and are skipped.
+    pub fn counteract<F>(&mut self, cid: CID, ca: &Counteract, mut pred: F) -> Vec<CounteractResult>
+        where F: FnMut(&Effect, &Source) -> bool
+    {
+        let mut sources: Vec<Source> = Vec::new();
+        if let Some(state) = self.creatures.get(&cid) {
+            for (eff, src) in state.status.sourced() {
+                if src.dc.is_some() && pred(eff, src) && !sources.contains(src) {
+                    sources.push(src.clone());
+                }
+            }
+        }
+        sources.sort_by(|a, b| (&a.name, a.cid).cmp(&(&b.name, b.cid)));
+        let cur = ca.by.and_then(|by| self.with_status(by, |status, world| status.current(world)));
+
+        sources.into_iter()
+            .map(|source| {
+                let mut check = Check::new(ca.stat, &ca.name, ca.modifier)
+                    .against(&source.name, source.dc.unwrap());
+                if let Some(cur) = &cur {
+                    check = check.roller(cur);
+                }
+                let check = check.roll(&mut self.rng);
+                let removed = if ca.succeeds(check.degree, source.rank.unwrap_or(0)) {
+                    self.remove_sourced_where(cid, |eff, src| *src == source && pred(eff, src))
+                        .into_iter()
+                        .map(|(eff, _)| eff)
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                CounteractResult { source, check, removed }
+            })
+            .collect()
+    }
+
+    // The active creature spends an action to keep up the effects it's sustaining that match,
+    // wherever they are. Returns how many it kept going.
+    pub fn sustain<F: FnMut(&Effect) -> bool>(&mut self, by: CID, mut pred: F) -> Result<usize, ActionError> {
+        self.act(by, &Activity::sustain())?;
+        let mut cids: Vec<CID> = self.creatures.keys().copied().collect();
+        cids.sort();
+        Ok(cids.into_iter()
+            .map(|cid| self.creatures.get_mut(&cid).unwrap().status.sustain(by, &mut pred))
+            .sum())
+    }
+}
@@ -0,0 +1,314 @@
+// Turn order. `World::initiative` holds every creature in the encounter from highest initiative
+// to lowest, and `Time::turn` is the index of the creature whose turn it is; passing the end of
+// the order starts the next round.
+
+use std::mem;
+
+use super::World;
+use super::action::ActionError;
+use super::affliction::AfflictionResult;
+use super::time::*;
+use crate::creature::{Budget, CheckResult, CID, Current, PersistentRoll, Side, Skill, State, Status};
+use crate::rng::{RandValue, Roll, Roller};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum InitiativeStat {
+    Perception,
+    Skill(Skill),
+}
+
+#[derive(Debug,Clone)]
+pub struct InitiativeRoll {
+    pub cid: CID,
+    pub stat: InitiativeStat,
+    pub roll: Roll,
+}
+
+#[derive(Debug,Clone)]
+pub struct TurnStart {
+    pub cid: CID,
+    pub current: Current,
+    // The recovery check, if the creature started its turn dying.
+    pub recovery: Option<CheckResult>,
+    pub afflictions: Vec<AfflictionResult>,
+}
+
+// A Ready action waiting on its trigger. It lapses at the start of the creature's next turn.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Readied {
+    pub trigger: String,
+    pub action: String,
+}
+
+impl InitiativeStat {
+    pub fn modifier(self, state: &State) -> isize {
+        match self {
+            InitiativeStat::Perception => state.creature.perception(),
+            InitiativeStat::Skill(skill) => state.creature.skill(skill),
+        }
+    }
+}
+
+// Ties go to enemies first, then anyone else, then the party.
+fn tie_rank(side: Side) -> usize {
+    match side {
+        Side::Enemy => 0,
+        Side::Neutral => 1,
+        Side::Party => 2,
+    }
+}
+
+impl<R> World<R> {
+    pub fn initiative(&self) -> impl Iterator<Item = CID> + '_ {
+        self.initiative.iter().copied()
+    }
+
+    // Whose turn it is, if an encounter is running.
+    pub fn active(&self) -> Option<CID> {
+        self.initiative.get(self.time.turn.0).copied()
+    }
+
+    pub fn is_delaying(&self, cid: CID) -> bool {
+        self.delaying.contains_key(&cid)
+    }
+
+    pub fn readied(&self, cid: CID) -> Option<&Readied> {
+        self.readied.get(&cid)
+    }
+
+    // The active creature prepares an action for later; see take_readied().
+    pub fn ready(&mut self, trigger: &str, action: &str) -> Option<CID> {
+        let cid = self.active()?;
+        self.readied.insert(cid, Readied { trigger: trigger.to_string(), action: action.to_string() });
+        Some(cid)
+    }
+
+    // Spends a readied action once its trigger happens.
+    pub fn take_readied(&mut self, cid: CID) -> Option<Readied> {
+        self.readied.remove(&cid)
+    }
+
+    fn advance(&mut self) {
+        if let Some(cid) = self.active() {
+            if let Some(state) = self.creatures.get_mut(&cid) {
+                state.budget.actions = 0;
+                state.budget.restricted = 0;
+            }
+        }
+        self.time.turn += Turns(1);
+        self.pass_turn();
+    }
+
+    // Hands the turn to whoever is now at `Time::turn`, wrapping past the end of the order to the
+    // top of the next round. Their turn still has to be started.
+    pub(super) fn pass_turn(&mut self) {
+        self.returned = None;
+        self.turn_budget = None;
+        if !self.initiative.is_empty() && self.time.turn.0 >= self.initiative.len() {
+            self.time.turn = Turn(0);
+            self.time.round += Rounds(1);
+        }
+    }
+
+    // Runs a Status hook with the Status taken out of the world, so the hook can borrow the
+    // world mutably.
+    pub(super) fn with_status<T, F>(&mut self, cid: CID, f: F) -> Option<T>
+        where F: FnOnce(&mut Status, &mut World<R>) -> T
+    {
+        let mut status = mem::replace(&mut self.creatures.get_mut(&cid)?.status, Status::new());
+        let res = f(&mut status, self);
+        if let Some(state) = self.creatures.get_mut(&cid) {
+            state.status = status;
+        }
+        Some(res)
+    }
+}
+
+impl<R: Roller> World<R> {
+    // Rolls everyone's initiative and starts a new encounter at the top of the order.
+    pub fn roll_initiative<F>(&mut self, mut stat: F) -> Vec<InitiativeRoll>
+        where F: FnMut(&State) -> InitiativeStat
+    {
+        // Roll in CID order so a seeded generator gives the same results every time.
+        let mut cids: Vec<CID> = self.creatures.keys().copied().collect();
+        cids.sort();
+
+        let mut rolls: Vec<InitiativeRoll> = cids.into_iter()
+            .map(|cid| {
+                let state = &self.creatures[&cid];
+                let st = stat(state);
+                let value = RandValue::D20 + st.modifier(state);
+                InitiativeRoll { cid, stat: st, roll: value.roll(&mut self.rng) }
+            })
+            .collect();
+        let creatures = &self.creatures;
+        rolls.sort_by_key(|r| {
+            (std::cmp::Reverse(r.roll.total), tie_rank(creatures[&r.cid].side), r.cid)
+        });
+
+        self.initiative = rolls.iter().map(|r| r.cid).collect();
+        self.time.turn = Turn(0);
+        self.delaying.clear();
+        self.readied.clear();
+        self.returned = None;
+        self.turn_budget = None;
+        rolls
+    }
+
+    // Begins the active creature's turn, returning what it has to work with. A creature that
+    // delayed through a whole round loses that turn and simply takes this one.
+    pub fn start_turn(&mut self) -> Option<TurnStart> {
+        let cid = self.active()?;
+        self.readied.remove(&cid);
+        self.delaying.remove(&cid);
+        let recovery = {
+            let state = self.creatures.get_mut(&cid)?;
+            state.status.recovery_check(&mut self.rng)
+        };
+        self.expire(cid, Hook::Start);
+        let afflictions = self.progress_afflictions(cid);
+        let current = self.with_status(cid, |status, world| status.before_turn(world))?;
+        let budget = Budget::for_turn(&current);
+        self.creatures.get_mut(&cid)?.budget = budget;
+        self.turn_budget = Some(budget);
+        Some(TurnStart { cid, current, recovery, afflictions })
+    }
+
+    // Ends the active creature's turn, applying its end-of-turn effects, and moves to the next.
+    pub fn end_turn(&mut self) -> Vec<PersistentRoll> {
+        let cid = match self.active() {
+            Some(cid) => cid,
+            None => return Vec::new(),
+        };
+        let rolls = if self.returned == Some(cid) {
+            Vec::new()
+        } else {
+            self.end_of_turn_effects(cid)
+        };
+        self.advance();
+        rolls
+    }
+
+    // The active creature Delays, which it can only do before spending anything. Its
+    // end-of-turn effects happen now, and it stays in its place in the order until it returns.
+    pub fn delay(&mut self) -> Result<Vec<PersistentRoll>, ActionError> {
+        let cid = match self.active() {
+            Some(cid) => cid,
+            None => return Ok(Vec::new()),
+        };
+        let budget = self.creatures[&cid].budget;
+        if self.turn_budget != Some(budget) {
+            return Err(ActionError::AlreadyActed(cid));
+        }
+        let rolls = self.end_of_turn_effects(cid);
+        self.delaying.insert(cid, budget);
+        self.advance();
+        Ok(rolls)
+    }
+
+    // A delaying creature steps back in before the creature that was about to act, taking that
+    // spot in the order from now on. Its turn already began, so it gets back the actions it
+    // Delayed with, and only its reaction may have been spent since.
+    pub fn return_from_delay(&mut self, cid: CID) -> Option<Budget> {
+        let saved = self.delaying.remove(&cid)?;
+        let idx = self.initiative.iter().position(|&c| c == cid)?;
+        self.initiative.remove(idx);
+        if idx < self.time.turn.0 {
+            self.time.turn.0 -= 1;
+        }
+        self.initiative.insert(self.time.turn.0, cid);
+        self.returned = Some(cid);
+        let budget = &mut self.creatures.get_mut(&cid)?.budget;
+        *budget = Budget { reaction: budget.reaction, ..saved };
+        Some(*budget)
+    }
+
+    fn end_of_turn_effects(&mut self, cid: CID) -> Vec<PersistentRoll> {
+        let rolls = self.with_status(cid, |status, world| status.after_turn_rolls(world))
+            .unwrap_or_default();
+        for roll in &rolls {
+            self.deal_damage(cid, &[roll.damage.result], false);
+        }
+        self.expire(cid, Hook::End);
+        rolls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creature::{Activity, Effect};
+    use crate::world::tests::{roll, spawn, world};
+
+    fn stunned<R>(world: &World<R>, cid: CID) -> Option<usize> {
+        world.creature(cid).unwrap().status.effects()
+            .find_map(|eff| match eff { Effect::Stunned { level } => Some(*level), _ => None })
+    }
+
+    #[test]
+    fn delay_and_return() {
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        let b = spawn(&mut w, Side::Enemy, 5);
+        roll(&mut w, &[15, 5]);
+        w.roll_initiative(|_| InitiativeStat::Perception);
+
+        w.start_turn().unwrap();
+        w.delay().unwrap();
+        assert!(w.is_delaying(a));
+        assert_eq!(w.start_turn().unwrap().cid, b);
+        let budget = w.return_from_delay(a).unwrap();
+        assert_eq!((budget.actions, budget.reaction), (3, true));
+        assert_eq!(w.active(), Some(a));
+        w.act(a, &Activity::stride()).unwrap();
+        w.end_turn();
+        assert_eq!(w.active(), Some(b));
+        assert_eq!(w.budget(b).unwrap().actions, 3);
+    }
+
+    #[test]
+    fn delay_after_acting() {
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        spawn(&mut w, Side::Enemy, 5);
+        roll(&mut w, &[15, 5]);
+        w.roll_initiative(|_| InitiativeStat::Perception);
+
+        w.start_turn().unwrap();
+        w.act(a, &Activity::stride()).unwrap();
+        assert_eq!(w.delay().unwrap_err(), ActionError::AlreadyActed(a));
+        assert!(!w.is_delaying(a));
+        assert_eq!(w.active(), Some(a));
+    }
+
+    #[test]
+    fn delay_while_stunned() {
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        spawn(&mut w, Side::Enemy, 5);
+        roll(&mut w, &[15, 5]);
+        w.roll_initiative(|_| InitiativeStat::Perception);
+        w.add_effect(a, Effect::Stunned { level: 4 });
+
+        assert_eq!(w.start_turn().unwrap().current.actions_gained, 0);
+        assert_eq!(stunned(&w, a), Some(1));
+        w.delay().unwrap();
+        w.start_turn().unwrap();
+        // Returning doesn't start the turn over, so stunned isn't paid off a second time.
+        assert_eq!(w.return_from_delay(a).unwrap().actions, 0);
+        assert_eq!(stunned(&w, a), Some(1));
+
+        let mut w = world();
+        let a = spawn(&mut w, Side::Party, 0);
+        spawn(&mut w, Side::Enemy, 5);
+        roll(&mut w, &[15, 5]);
+        w.roll_initiative(|_| InitiativeStat::Perception);
+        w.add_effect(a, Effect::Stunned { level: 1 });
+
+        assert_eq!(w.start_turn().unwrap().current.actions_gained, 2);
+        assert_eq!(stunned(&w, a), None);
+        w.delay().unwrap();
+        w.start_turn().unwrap();
+        assert_eq!(w.return_from_delay(a).unwrap().actions, 2);
+    }
+}
@@ -0,0 +1,150 @@
+// Saving throws against spells, hazards and the like. Damage is rolled once and shared by every
+// creature that saves, as for an area spell.
+
+use super::World;
+use crate::creature::*;
+use crate::rng::Roller;
+
+#[derive(Debug,Clone)]
+pub struct SavingThrow {
+    pub name: String,
+    pub save: Save,
+    pub dc: isize,
+    // What the effects and persistent damage are from; by default just the name and DC.
+    pub source: Source,
+    // Basic save damage: none on a critical success, half on a success, all of it on a failure
+    // and double on a critical failure.
+    pub damage: Option<DamagePacket>,
+    // Effects attached at exactly the given degree, except that a critical failure with none
+    // listed takes those of a failure.
+    pub effects: Vec<(Degree, Effect)>,
+}
+
+#[derive(Debug,Clone)]
+pub struct SaveResult {
+    pub cid: CID,
+    pub check: CheckResult,
+    pub dealt: Option<PacketResult>,
+    // Only those actually attached, and not any the creature was immune to.
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Debug,Clone)]
+pub struct SaveOutcome {
+    pub damage: Vec<DamageRoll>,
+    pub results: Vec<SaveResult>,
+}
+
+impl SavingThrow {
+    pub fn new(name: &str, save: Save, dc: isize) -> Self {
+        let source = Source::new(name).dc(dc);
+        Self { name: name.to_string(), save, dc, source, damage: None, effects: Vec::new() }
+    }
+
+    pub fn by(mut self, cid: CID) -> Self {
+        self.source = self.source.by(cid);
+        self
+    }
+
+    pub fn rank(mut self, rank: usize) -> Self {
+        self.source = self.source.rank(rank);
+        self
+    }
+
+    pub fn with_trait(mut self, tr: EffectTrait) -> Self {
+        self.source = self.source.with_trait(tr);
+        self
+    }
+
+    pub fn basic(mut self, damage: DamagePacket) -> Self {
+        self.damage = Some(damage);
+        self
+    }
+
+    pub fn on(mut self, degree: Degree, eff: Effect) -> Self {
+        self.effects.push((degree, eff));
+        self
+    }
+}
+
+// Scales rolled damage for a basic save. Halving rounds the total down, not each part.
+pub fn basic_damage(results: &[DamageResult], degree: Degree) -> Vec<DamageResult> {
+    match degree {
+        Degree::CriticalSuccess => Vec::new(),
+        Degree::Success => {
+            let total: usize = results.iter().map(|r| r.amount + r.prec_amount).sum();
+            let mut halved: Vec<DamageResult> = results.iter()
+                .map(|r| DamageResult { amount: r.amount / 2, prec_amount: r.prec_amount / 2, ..*r })
+                .collect();
+            // Halving each part loses a half for every odd one; those go back, to ordinary damage
+            // before precision damage, until the total is right.
+            let mut short = total / 2 - halved.iter().map(|r| r.amount + r.prec_amount).sum::<usize>();
+            for (r, orig) in halved.iter_mut().zip(results) {
+                if short > 0 && orig.amount % 2 == 1 {
+                    r.amount += 1;
+                    short -= 1;
+                }
+            }
+            for (r, orig) in halved.iter_mut().zip(results) {
+                if short > 0 && orig.prec_amount % 2 == 1 {
+                    r.prec_amount += 1;
+                    short -= 1;
+                }
+            }
+            halved
+        },
+        Degree::Failure => results.to_vec(),
+        Degree::CriticalFailure => results.iter()
+            .map(|r| DamageResult { amount: r.amount * 2, prec_amount: r.prec_amount * 2, ..*r })
+            .collect(),
+    }
+}
+
+impl<R: Roller> World<R> {
+    // Every creature in `targets` saves against the same effect.
+    pub fn saving_throw(&mut self, st: &SavingThrow, targets: &[CID]) -> SaveOutcome {
+        let damage = match &st.damage {
+            Some(packet) => packet.roll(&mut self.rng),
+            None => Vec::new(),
+        };
+        let rolled: Vec<DamageResult> = damage.iter().map(|r| r.result).collect();
+
+        let results = targets.iter()
+            .filter_map(|&cid| self.save_one(st, cid, &rolled))
+            .collect();
+        SaveOutcome { damage, results }
+    }
+
+    fn save_one(&mut self, st: &SavingThrow, cid: CID, rolled: &[DamageResult]) -> Option<SaveResult> {
+        let cur = self.with_status(cid, |status, world| status.current(world))?;
+        let check = self.creatures[&cid].creature.save_check(st.save)
+            .roller(&cur)
+            .against(&st.name, st.dc)
+            .roll(&mut self.rng);
+
+        let crit = check.degree == Degree::CriticalFailure;
+        let mut dealt = None;
+        if let Some(packet) = &st.damage {
+            dealt = self.deal_damage(cid, &basic_damage(rolled, check.degree), crit);
+            if check.degree <= Degree::Failure {
+                let persistent = packet.persistent.iter()
+                    .map(|d| if crit { d.critical(Default::default()) } else { d.clone() })
+                    .collect();
+                self.add_persistent(cid, persistent, &st.source);
+            }
+        }
+
+        let listed = |at: Degree| st.effects.iter().any(|(degree, _)| *degree == at);
+        let degree = match check.degree {
+            Degree::CriticalFailure if !listed(Degree::CriticalFailure) => Degree::Failure,
+            degree => degree,
+        };
+        let effects = st.effects.iter()
+            .filter(|(at, _)| *at == degree)
+            .filter(|(_, eff)| self.add_effect_from(cid, eff.clone(), st.source.clone()))
+            .map(|(_, eff)| eff.clone())
+            .collect();
+
+        Some(SaveResult { cid, check, dealt, effects })
+    }
+}
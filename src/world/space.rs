@@ -1,11 +1,24 @@
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
 pub struct Squares(pub usize);
-#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+#[derive(Debug,Clone,Copy,Default,PartialEq,Eq,PartialOrd,Ord,Hash)]
 pub struct Feet(pub usize);
 
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
 pub struct Location(pub util::V2i);
 
+impl Location {
+    pub fn offset(self, dx: isize, dy: isize) -> Location {
+        Location(self.0 + util::V2i::new(dx, dy))
+    }
+
+    // Squares between the two, counting diagonals as one; adjacent squares are 1 apart.
+    pub fn distance(self, other: Location) -> Squares {
+        let dx = (self.0.x - other.0.x).abs();
+        let dy = (self.0.y - other.0.y).abs();
+        Squares(std::cmp::max(dx, dy) as usize)
+    }
+}
+
 impl Squares {
     pub const FEET_PER: usize = 5;
 }
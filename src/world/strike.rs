@@ -0,0 +1,168 @@
+// The Strike action: one attack roll against AC, and damage if it hits. Splash lands on the target
+// and everything around it unless the Strike critically misses.
+
+use std::collections::BTreeSet;
+
+use super::World;
+use super::action::{ActionError, Opportunity};
+use super::space::{Feet, Squares};
+use crate::creature::*;
+use crate::rng::Roller;
+
+#[derive(Debug,Clone)]
+pub struct StrikeResult {
+    pub check: CheckResult,
+    // Empty on a miss.
+    pub damage: Vec<DamageRoll>,
+    pub splash: Option<DamageRoll>,
+    // What the target took, splash included.
+    pub dealt: Option<PacketResult>,
+    // Persistent damage now attached to the target.
+    pub persistent: Vec<Damage>,
+    // Everything else the splash hit, in CID order.
+    pub splashed: Vec<(CID, PacketResult)>,
+    pub opportunities: Vec<Opportunity>,
+}
+
+impl<R: Roller> World<R> {
+    // `attacker` Strikes `target` with the attack at index `attack` in its stat block.
+    pub fn strike(&mut self, attacker: CID, attack: usize, target: CID) -> Result<StrikeResult, ActionError> {
+        self.resolve_strike(attacker, attack, target, None)
+    }
+
+    // A Strike made as the reaction to `opp`, e.g. Attack of Opportunity, against the creature that
+    // provoked it. It spends the reaction instead of actions, and the multiple attack penalty
+    // neither applies to it nor counts it.
+    pub fn reactive_strike(&mut self, opp: &Opportunity, attack: usize) -> Result<StrikeResult, ActionError> {
+        self.resolve_strike(opp.cid, attack, opp.actor, Some(opp))
+    }
+
+    fn resolve_strike(&mut self, attacker: CID, attack: usize, target: CID, reaction: Option<&Opportunity>) -> Result<StrikeResult, ActionError> {
+        let (atk, ability, bonus, made, level) = {
+            let state = self.creatures.get(&attacker).ok_or(ActionError::NoSuchCreature(attacker))?;
+            let atk = state.creature.attacks.get(attack).ok_or(ActionError::NoSuchAttack(attack))?.clone();
+            let ability = state.creature.attack_ability(&atk);
+            let bonus = state.creature.attack_bonus(&atk);
+            (atk, ability, bonus, state.budget.attacks, state.creature.level)
+        };
+        let range_penalty = self.range_penalty(attacker, &atk, target)?;
+
+        let (map, opportunities) = match reaction {
+            Some(opp) => {
+                self.react(opp)?;
+                (0, Vec::new())
+            },
+            None => (atk.map(made), self.act(attacker, &atk.activity())?),
+        };
+
+        let acur = self.with_status(attacker, |status, world| status.current(world)).unwrap();
+        let tcur = self.with_status(target, |status, world| status.current(world)).unwrap();
+        let ac = self.creatures[&target].creature.ac();
+        let check = Check::new(Stat::Attack(ability), &atk.name, bonus)
+            .modifier("multiple attack penalty", map)
+            .modifier("range penalty", range_penalty)
+            .roller(&acur)
+            .against("AC", ac)
+            .target(&tcur)
+            .roll(&mut self.rng);
+
+        let crit = check.degree == Degree::CriticalSuccess;
+        let packet = match check.degree {
+            Degree::CriticalSuccess => Some(atk.damage.critical(atk.crit_traits())),
+            Degree::Success => Some(atk.damage.clone()),
+            _ => None,
+        };
+        let (damage, mut results, persistent) = match packet {
+            Some(packet) => {
+                let damage = packet.roll(&mut self.rng);
+                let mut results: Vec<DamageResult> = damage.iter().map(|r| r.result).collect();
+                // Only melee Strikes add Strength to damage, and so only they take modifiers to it.
+                if atk.is_melee() {
+                    let adjust = acur.modifiers.total(Stat::Damage(Ability::Str));
+                    adjust_damage(&mut results, if crit { adjust * 2 } else { adjust });
+                }
+                (damage, results, packet.persistent)
+            },
+            None => (Vec::new(), Vec::new(), Vec::new()),
+        };
+        let splash = match &atk.damage.splash {
+            Some(sp) if check.degree != Degree::CriticalFailure => Some(sp.damage.roll(&mut self.rng)),
+            _ => None,
+        };
+        if let Some(sp) = &splash {
+            results.push(sp.result);
+        }
+
+        let dealt = if results.is_empty() { None } else { self.deal_damage(target, &results, crit) };
+        // Persistent damage is from the attack, counteracted at the attacker's level and against
+        // the DC of its attack bonus.
+        let source = Source::new(&atk.name)
+            .by(attacker)
+            .rank(Source::rank_of_level(level))
+            .dc(Creature::dc(bonus));
+        let persistent = self.add_persistent(target, persistent, &source);
+        let splashed = match &splash {
+            Some(sp) => self.splash_around(target, sp.result),
+            None => Vec::new(),
+        };
+
+        Ok(StrikeResult { check, damage, splash, dealt, persistent, splashed, opportunities })
+    }
+
+    // Deals splash to every creature in the squares around `target`, but not to the target itself.
+    fn splash_around(&mut self, target: CID, result: DamageResult) -> Vec<(CID, PacketResult)> {
+        let state = &self.creatures[&target];
+        let hit: BTreeSet<CID> = Splash::squares(state.loc, state.creature.size.space())
+            .into_iter()
+            .filter_map(|loc| self.square(loc))
+            .flat_map(|sq| sq.occupants.iter().copied())
+            .filter(|&cid| cid != target)
+            .collect();
+        hit.into_iter()
+            .filter_map(|cid| self.deal_damage(cid, &[result], false).map(|dealt| (cid, dealt)))
+            .collect()
+    }
+
+    // Melee attacks must be within reach; ranged attacks take -2 for each full range increment
+    // past the first, out to MAX_INCREMENTS.
+    fn range_penalty(&self, attacker: CID, atk: &Attack, target: CID) -> Result<isize, ActionError> {
+        let tstate = self.creatures.get(&target).ok_or(ActionError::NoSuchCreature(target))?;
+        let dist = self.creatures[&attacker].distance(tstate);
+        match atk.range {
+            AttackRange::Melee { .. } => {
+                if dist <= Squares::from(atk.reach().unwrap()) {
+                    Ok(0)
+                } else {
+                    Err(ActionError::OutOfRange { target })
+                }
+            },
+            AttackRange::Ranged { increment } => {
+                let Feet(ft) = dist.into();
+                let increments = std::cmp::max(ft.div_ceil(increment.0), 1);
+                if increments > Attack::MAX_INCREMENTS {
+                    Err(ActionError::OutOfRange { target })
+                } else {
+                    Ok(-2 * (increments as isize - 1))
+                }
+            },
+        }
+    }
+}
+
+// Adds a flat modifier to rolled damage: bonuses to the first component, penalties taken from each
+// in turn, never below 1 in total.
+fn adjust_damage(results: &mut [DamageResult], by: isize) {
+    if by >= 0 {
+        if let Some(first) = results.first_mut() {
+            first.amount += by as usize;
+        }
+        return;
+    }
+    let total: usize = results.iter().map(|r| r.amount + r.prec_amount).sum();
+    let mut left = std::cmp::min(by.unsigned_abs(), total.saturating_sub(1));
+    for r in results.iter_mut() {
+        let take = std::cmp::min(r.amount, left);
+        r.amount -= take;
+        left -= take;
+    }
+}
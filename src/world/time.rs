@@ -1,6 +1,8 @@
 use std::ops::{Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign};
 use std::time::Duration;
 
+use crate::creature::CID;
+
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
 pub struct Round(pub usize);  // Essentially "Instant" at the round level
 #[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
@@ -16,6 +18,88 @@ pub struct Time {
     pub turn: Turn,
 }
 
+// The two points in a creature's turn that effects can end at.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Hook {
+    Start,
+    End,
+}
+
+// How long an effect lasts.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum Lasts {
+    // Until something removes it.
+    Forever,
+    // Until the end of the affected creature's turn in this round or later.
+    Until(Round),
+    // Until the `hook` of `of`'s turn, letting `turns` of them pass first: "until the end of your
+    // next turn", given during your turn, is Turns { of: you, hook: End, turns: Turns(1) }, and "until
+    // the start of the caster's next turn" is Turns { of: caster, hook: Start, turns: Turns::ZERO }.
+    Turns { of: CID, hook: Hook, turns: Turns },
+    // Ends at the end of any of `by`'s turns in which it didn't Sustain, and at `until` regardless.
+    Sustained { by: CID, until: Round, sustained: bool },
+}
+
+impl Lasts {
+    // Sustained for up to `max`, starting now; the turn it starts in counts as sustained.
+    pub fn sustained(by: CID, now: Round, max: Rounds) -> Self {
+        Lasts::Sustained { by, until: now + max, sustained: true }
+    }
+
+    // The creature whose turns this is counted in, if not the affected one's.
+    pub fn of(&self) -> Option<CID> {
+        match *self {
+            Lasts::Turns { of, .. } => Some(of),
+            Lasts::Sustained { by, .. } => Some(by),
+            _ => None,
+        }
+    }
+
+    // Called at the `hook` of `turn_of`'s turn (`own` if that's the affected creature), in round
+    // `now`. Counts down, and returns whether the effect goes on.
+    pub fn tick(&mut self, turn_of: CID, own: bool, hook: Hook, now: Round) -> bool {
+        match self {
+            Lasts::Forever => true,
+            Lasts::Until(t) => !(own && hook == Hook::End && *t <= now),
+            Lasts::Turns { of, hook: at, turns } => {
+                if *of != turn_of || *at != hook {
+                    true
+                } else if *turns == Turns::ZERO {
+                    false
+                } else {
+                    *turns -= Turns(1);
+                    true
+                }
+            },
+            Lasts::Sustained { by, until, sustained } => {
+                if *by != turn_of || hook != Hook::End {
+                    true
+                } else if !*sustained || *until <= now {
+                    false
+                } else {
+                    *sustained = false;
+                    true
+                }
+            },
+        }
+    }
+
+    // Whether this certainly ends later than `other`, or None if that depends on the initiative
+    // order.
+    pub fn outlasts(&self, other: &Lasts) -> Option<bool> {
+        use Lasts::*;
+
+        match (*self, *other) {
+            (_, Forever) => Some(false),
+            (Forever, _) => Some(true),
+            (Until(a), Until(b)) => Some(a > b),
+            (Turns { of: a, hook: ha, turns: ta }, Turns { of: b, hook: hb, turns: tb }) if a == b && ha == hb => Some(ta > tb),
+            (Sustained { until: a, .. }, Sustained { until: b, .. }) => Some(a > b),
+            _ => None,
+        }
+    }
+}
+
 macro_rules! derive_op {
     ($type:ident, $rhs:ident, $trait:ident, $meth:ident, $binop:tt, $atrait:ident, $assn: ident, $abinop: tt) => {
         impl $trait<$rhs> for $type {